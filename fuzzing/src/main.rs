@@ -5,8 +5,6 @@ use turbo_bernd::http::Request;
 
 fn main() {
     fuzz!(|data: &[u8]| {
-        if let Ok(s) = std::str::from_utf8(data) {
-            let _ = Request::parse(&s);
-        }
+        let _ = Request::parse(data);
     });
 }
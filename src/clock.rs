@@ -0,0 +1,55 @@
+use crate::http::HttpDate;
+use std::{
+    cell::RefCell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+thread_local! {
+    // Each worker keeps its own copy, so formatting needs no locking
+    static CACHED: RefCell<(u64, String)> = const { RefCell::new((u64::MAX, String::new())) };
+}
+
+/// The current time formatted for the `Date` header.
+pub(crate) fn http_date() -> String {
+    format_cached(SystemTime::now())
+}
+
+// Dates only have a resolution of seconds, so the string is formatted again
+// once the second changes
+fn format_cached(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    CACHED.with(|cached| {
+        let mut cached = cached.borrow_mut();
+
+        if cached.0 != seconds {
+            *cached = (seconds, HttpDate(time).to_string());
+        }
+
+        cached.1.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_once_per_second() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        assert_eq!(format_cached(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            format_cached(time + Duration::from_millis(999)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            format_cached(time + Duration::from_secs(1)),
+            "Sun, 06 Nov 1994 08:49:38 GMT"
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{clock, http, Config};
+
+use std::{
+    fmt,
+    io::{self, prelude::*, BufWriter},
+};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Http(http::Error),
+    /// The request was answered before its body was read, so the rest of
+    /// the stream can't be used and is discarded until the connection is
+    /// closed.
+    Rejected(Box<http::Response>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Http(e) => write!(f, "{}", e),
+            Error::Rejected(_) => write!(f, "Rejected before reading the body"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(e: http::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// Buffers reads from a stream so that requests can be read in full,
+/// independent of how the bytes are split across reads.
+pub struct Connection<S> {
+    stream: S,
+    buffer: Vec<u8>,
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn new(stream: S) -> Connection<S> {
+        Connection {
+            stream,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Blocks until bytes of the next request have arrived. Returns `false`
+    /// if the peer closed the connection instead.
+    pub fn wait_for_data(&mut self) -> io::Result<bool> {
+        if !self.buffer.is_empty() {
+            return Ok(true);
+        }
+
+        Ok(self.fill_buffer()? > 0)
+    }
+
+    /// Reads the next request from the stream.
+    ///
+    /// Clients that wait for `100 Continue` get it once `precheck` has
+    /// seen the header and returned `None`. A response it returns instead
+    /// is passed on as `Error::Rejected`.
+    ///
+    /// Returns `Ok(None)` if the peer closed the connection before sending
+    /// anything. Bytes following the request stay buffered for the next call.
+    pub fn read_request<F>(
+        &mut self,
+        config: &Config,
+        precheck: F,
+    ) -> Result<Option<http::Request>, Error>
+    where
+        F: FnOnce(&http::Request) -> Option<http::Response>,
+    {
+        let mut parser = http::RequestParser::new(config.max_header_size, config.max_body_size);
+        let mut precheck = Some(precheck);
+
+        loop {
+            match parser.feed(&self.buffer)? {
+                http::Parsed::Complete(request, consumed) => {
+                    self.buffer.drain(..consumed);
+                    return Ok(Some(*request));
+                }
+                // The parser keeps what it needs of incomplete requests
+                http::Parsed::Incomplete => self.buffer.clear(),
+            }
+
+            if let Some(head) = parser.head().filter(|head| head.expects_continue()) {
+                if let Some(precheck) = precheck.take() {
+                    if let Some(response) = precheck(head) {
+                        return Err(Error::Rejected(Box::new(response)));
+                    }
+
+                    self.write(http::Response::new(http::Status::Continue))?;
+                }
+            }
+
+            if self.fill_buffer()? == 0 {
+                if parser.is_empty() {
+                    return Ok(None);
+                }
+
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+    }
+
+    /// Writes the response, copying streamed bodies to the socket as they
+    /// are read. `Date` and `Server` headers are added unless the response
+    /// already has them.
+    pub fn write_response(
+        &mut self,
+        mut response: http::Response,
+        config: &Config,
+    ) -> io::Result<()> {
+        add_standard_headers(&mut response, config);
+        self.write(response)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn write(&mut self, response: http::Response) -> io::Result<()> {
+        let mut writer = BufWriter::new(&mut self.stream);
+        response.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    fn fill_buffer(&mut self) -> io::Result<usize> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        let read = loop {
+            match self.stream.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+
+        self.buffer.extend_from_slice(&chunk[..read]);
+
+        Ok(read)
+    }
+}
+
+/// Adds the headers every final response carries, unless the handler
+/// already set them. They depend on the configuration and the time of
+/// sending, so `Response::write_to` leaves them out.
+pub(crate) fn add_standard_headers(response: &mut http::Response, config: &Config) {
+    if !response.headers.contains_key("Date") {
+        response.headers.insert("Date", clock::http_date());
+    }
+
+    if let Some(server) = &config.server_header {
+        if !response.headers.contains_key("Server") {
+            response.headers.insert("Server", server.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Method;
+
+    // Hands out the input in small pieces to simulate partial reads
+    struct MockStream {
+        input: Vec<u8>,
+        position: usize,
+        read_size: usize,
+        output: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(input: &[u8], read_size: usize) -> MockStream {
+            MockStream {
+                input: input.to_vec(),
+                position: 0,
+                read_size,
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let end = (self.position + self.read_size)
+                .min(self.input.len())
+                .min(self.position + buf.len());
+            let read = end - self.position;
+            buf[..read].copy_from_slice(&self.input[self.position..end]);
+            self.position = end;
+            Ok(read)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_request_across_partial_reads() {
+        let body = "a".repeat(3000);
+        let input = format!(
+            "POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut connection = Connection::new(MockStream::new(input.as_bytes(), 7));
+
+        let request = connection
+            .read_request(&Config::new(0), |_| None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.body, body.as_bytes());
+    }
+
+    #[test]
+    fn read_request_reads_exactly_content_length() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\ncontent-length: 5\r\n\r\nHelloGET";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+
+        let request = connection
+            .read_request(&Config::new(0), |_| None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.body, b"Hello");
+        assert_eq!(connection.buffer, b"GET");
+    }
+
+    #[test]
+    fn read_request_without_body() {
+        let input = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+
+        let request = connection
+            .read_request(&Config::new(0), |_| None)
+            .unwrap()
+            .unwrap();
+
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn read_request_keeps_binary_body() {
+        let mut input = b"POST / HTTP/1.1\r\n\
+            Host: example.com\r\nContent-Length: 4\r\n\r\n"
+            .to_vec();
+        input.extend_from_slice(&[0x00, 0xff, 0x0d, 0x0a]);
+        let mut connection = Connection::new(MockStream::new(&input, 3));
+
+        let request = connection
+            .read_request(&Config::new(0), |_| None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.body, vec![0x00, 0xff, 0x0d, 0x0a]);
+    }
+
+    #[test]
+    fn read_request_decodes_chunked_body_across_partial_reads() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nHello\r\n0\r\nChecksum: abc\r\n\r\n";
+        let mut connection = Connection::new(MockStream::new(input, 3));
+
+        let request = connection
+            .read_request(&Config::new(0), |_| None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.body, b"Hello");
+        assert_eq!(request.header_value("Checksum"), Some("abc"));
+        assert!(connection.buffer.is_empty());
+    }
+
+    #[test]
+    fn read_request_rejects_large_chunked_body() {
+        let input = format!(
+            "POST / HTTP/1.1\r\n\
+                Host: example.com\r\nTransfer-Encoding: chunked\r\n\r\n40\r\n{}\r\n",
+            "a".repeat(64)
+        );
+        let mut connection = Connection::new(MockStream::new(input.as_bytes(), 1024));
+        let config = Config::new(0).max_body_size(32);
+
+        let result = connection.read_request(&config, |_| None);
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(ref e)) if e.kind() == http::ErrorKind::BodyTooLarge
+        ));
+    }
+
+    #[test]
+    fn read_request_rejects_content_length_with_transfer_encoding() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\
+            Transfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+
+        let result = connection.read_request(&Config::new(0), |_| None);
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(ref e)) if e.kind() == http::ErrorKind::ConflictingFraming
+        ));
+    }
+
+    #[test]
+    fn read_request_sends_continue_before_reading_body() {
+        let header = "POST /upload HTTP/1.1\r\n\
+            Host: example.com\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n";
+        let input = format!("{}Hello", header);
+        let mut connection = Connection::new(MockStream::new(input.as_bytes(), header.len()));
+
+        let request = connection
+            .read_request(&Config::new(0), |request| {
+                assert!(request.body.is_empty());
+                None
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(request.body, b"Hello");
+        assert_eq!(connection.stream.output, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn read_request_rejects_without_reading_body() {
+        let header = "POST /upload HTTP/1.1\r\n\
+            Host: example.com\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n";
+        let input = format!("{}Hello", header);
+        let mut connection = Connection::new(MockStream::new(input.as_bytes(), header.len()));
+
+        let result = connection.read_request(&Config::new(0), |_| {
+            Some(http::Response::new(http::Status::Unauthorized))
+        });
+
+        assert!(matches!(
+            result,
+            Err(Error::Rejected(ref response)) if response.status == http::Status::Unauthorized
+        ));
+        assert!(connection.stream.output.is_empty());
+        assert_eq!(connection.stream.position, header.len());
+    }
+
+    #[test]
+    fn read_request_handles_pipelined_requests() {
+        let input = b"GET /one HTTP/1.1\r\nHost: example.com\r\n\r\n\
+            POST /two HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\r\nabc\
+            GET /three HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+        let config = Config::new(0);
+
+        let paths: Vec<String> = (0..3)
+            .map(|_| {
+                connection
+                    .read_request(&config, |_| None)
+                    .unwrap()
+                    .unwrap()
+                    .uri
+                    .path
+            })
+            .collect();
+
+        assert_eq!(paths, vec!["/one", "/two", "/three"]);
+        assert!(connection
+            .read_request(&config, |_| None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn write_response_adds_date_and_server() {
+        let mut connection = Connection::new(MockStream::new(b"", 1024));
+        let config = Config::new(0).server_header(Some("test"));
+
+        let response = http::Response::new(http::Status::Ok);
+        connection.write_response(response, &config).unwrap();
+        let output = String::from_utf8(connection.stream.output.split_off(0)).unwrap();
+        assert!(output.contains("\r\nDate: "));
+        assert!(output.contains("\r\nServer: test\r\n"));
+
+        let response = http::Response::new(http::Status::Ok).header(("Server", "custom"));
+        connection.write_response(response, &config).unwrap();
+        let output = String::from_utf8(connection.stream.output.split_off(0)).unwrap();
+        assert!(output.contains("\r\nServer: custom\r\n"));
+        assert_eq!(output.matches("Server: ").count(), 1);
+    }
+
+    #[test]
+    fn wait_for_data_keeps_what_arrived() {
+        let mut connection = Connection::new(MockStream::new(
+            b"GET / HTTP/1.1\r\n\
+            Host: example.com\r\n\r\n",
+            4,
+        ));
+
+        assert!(connection.wait_for_data().unwrap());
+        assert!(connection.wait_for_data().unwrap());
+        assert_eq!(connection.buffer, b"GET ");
+
+        let request = connection.read_request(&Config::new(0), |_| None).unwrap();
+        assert_eq!(request.unwrap().uri.path, "/");
+        assert!(!connection.wait_for_data().unwrap());
+    }
+
+    #[test]
+    fn read_request_returns_none_on_closed_connection() {
+        let mut connection = Connection::new(MockStream::new(b"", 1024));
+
+        let request = connection.read_request(&Config::new(0), |_| None).unwrap();
+
+        assert!(request.is_none());
+    }
+
+    #[test]
+    fn read_request_rejects_large_header() {
+        let input = format!(
+            "GET / HTTP/1.1\r\n\
+            Host: example.com\r\nX-Big: {}\r\n\r\n",
+            "a".repeat(100)
+        );
+        let mut connection = Connection::new(MockStream::new(input.as_bytes(), 1024));
+        let config = Config::new(0).max_header_size(64);
+
+        let result = connection.read_request(&config, |_| None);
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(ref e)) if e.kind() == http::ErrorKind::HeaderTooLarge
+        ));
+    }
+
+    #[test]
+    fn read_request_rejects_large_body() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 100\r\n\r\n";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+        let config = Config::new(0).max_body_size(64);
+
+        let result = connection.read_request(&config, |_| None);
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(ref e)) if e.kind() == http::ErrorKind::BodyTooLarge
+        ));
+    }
+
+    #[test]
+    fn read_request_rejects_truncated_body() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 10\r\n\r\nHello";
+        let mut connection = Connection::new(MockStream::new(input, 1024));
+
+        let result = connection.read_request(&Config::new(0), |_| None);
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+}
@@ -1,5 +1,27 @@
-use mime;
-use std::{collections::HashMap, fmt};
+mod error;
+mod headers;
+mod parser;
+mod status;
+mod typed_headers;
+mod uri;
+
+pub use error::{Error, ErrorKind};
+pub use headers::{HeaderIter, HeaderMap};
+pub use parser::{Parsed, RequestParser};
+pub use status::{ResponseClass, Status, UnregisteredCode};
+pub use typed_headers::{
+    Authorization, ByteRange, CacheControl, EntityTag, Host, HttpDate, IfNoneMatch, Quality, Range,
+    TypedHeaders,
+};
+pub use uri::{QueryParams, RequestTarget, Uri};
+
+use crate::parser_combinator as grammar;
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    str,
+};
 
 // "http://www.example.com/hello.txt":
 
@@ -20,35 +42,21 @@ use std::{collections::HashMap, fmt};
 
 //      Hello World! My payload includes a trailing CRLF.
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
-    UnsupportedVersion,
-    UnknownMethod,
-    MalformedRequest,
-}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let error = match self {
-            Error::UnsupportedVersion => "Unsupported version",
-            Error::UnknownMethod => "Unknown method",
-            Error::MalformedRequest => "Malformed request",
-        };
-
-        write!(f, "{}", error)
-    }
-}
+const HEADER_TERMINATOR: &[u8] = b"\r\n\r\n";
+const CHUNK_SIZE: usize = 8192;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Version {
+    OneDotZero,
     OneDotOne,
 }
 
 impl Version {
     fn parse(input: &str) -> Result<Self, Error> {
         match input {
+            "HTTP/1.0" => Ok(Self::OneDotZero),
             "HTTP/1.1" => Ok(Self::OneDotOne),
-            _ => Err(Error::UnsupportedVersion),
+            _ => Err(ErrorKind::UnsupportedVersion.into()),
         }
     }
 }
@@ -56,6 +64,7 @@ impl Version {
 impl fmt::Display for Version {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let version = match self {
+            Self::OneDotZero => "HTTP/1.0",
             Self::OneDotOne => "HTTP/1.1",
         };
 
@@ -63,56 +72,7 @@ impl fmt::Display for Version {
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Status {
-    Ok,
-    BadRequest,
-    Unauthorized,
-    Forbidden,
-    NotFound,
-    MethodNotAllowed,
-    VersionNotSupported,
-}
-
-impl fmt::Display for Status {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let status = match self {
-            Self::Ok => "200 OK",
-            Self::BadRequest => "400 Bad Request",
-            Self::Unauthorized => "401 Unauthorized",
-            Self::Forbidden => "402 Forbidden",
-            Self::NotFound => "404 Not Found",
-            Self::MethodNotAllowed => "405 Method Not Allowed",
-            Self::VersionNotSupported => "505 HTTP Version Not Supported",
-        };
-
-        write!(f, "{}", status)
-    }
-}
-
-pub enum ResponseClass {
-    Informational,
-    Successful,
-    Redirection,
-    ClientError,
-    ServerError,
-}
-
-impl ResponseClass {
-    fn new(status: &Status) -> ResponseClass {
-        match status {
-            Status::Ok => Self::Successful,
-            Status::BadRequest => Self::ClientError,
-            Status::Unauthorized => Self::ClientError,
-            Status::Forbidden => Self::ClientError,
-            Status::NotFound => Self::ClientError,
-            Status::MethodNotAllowed => Self::ClientError,
-            Status::VersionNotSupported => Self::ServerError,
-        }
-    }
-}
-
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Method {
     Get,
     Head,
@@ -122,6 +82,10 @@ pub enum Method {
     Connect,
     Options,
     Trace,
+    Patch,
+    /// Any other method, like WebDAV's `PROPFIND`. Methods are case-sensitive,
+    /// so `get` is an extension method as well.
+    Extension(String),
 }
 
 impl Method {
@@ -135,49 +99,67 @@ impl Method {
             "CONNECT" => Self::Connect,
             "OPTIONS" => Self::Options,
             "TRACE" => Self::Trace,
-            _ => return Err(Error::UnknownMethod),
+            "PATCH" => Self::Patch,
+            _ if !input.is_empty() && input.bytes().all(grammar::is_tchar) => {
+                Self::Extension(input.to_string())
+            }
+            _ => return Err(ErrorKind::InvalidMethod.into()),
         };
 
         Ok(method)
     }
-}
 
-#[derive(Debug, Eq, PartialEq, Hash)]
-pub struct Uri {
-    pub path: String,
-}
+    /// Whether the method only retrieves, so that a request has no effect on
+    /// the server. Nothing is known about extension methods, so they aren't.
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Self::Get | Self::Head | Self::Options | Self::Trace)
+    }
 
-impl Uri {
-    pub fn new(path: &str) -> Uri {
-        Uri {
-            path: path.to_string(),
-        }
+    /// Whether sending a request several times has the same effect as
+    /// sending it once, which makes it fine to retry.
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self, Self::Put | Self::Delete)
     }
+}
 
-    fn parse(input: &str) -> Result<Self, Error> {
-        Ok(Self {
-            path: input.to_string(),
-        })
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = match self {
+            Self::Get => "GET",
+            Self::Head => "HEAD",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Delete => "DELETE",
+            Self::Connect => "CONNECT",
+            Self::Options => "OPTIONS",
+            Self::Trace => "TRACE",
+            Self::Patch => "PATCH",
+            Self::Extension(method) => method,
+        };
+
+        write!(f, "{}", method)
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Request {
     pub method: Method,
+    pub target: RequestTarget,
     pub uri: Uri,
     pub version: Version,
-    pub headers: HashMap<String, String>,
-    pub body: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
 }
 
 impl Request {
     fn new(uri: &str) -> Request {
         Request {
             method: Method::Get,
+            target: RequestTarget::Origin,
             uri: Uri::parse(uri).unwrap(),
             version: Version::OneDotOne,
-            headers: HashMap::new(),
-            body: "".to_string(),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
         }
     }
 
@@ -193,30 +175,235 @@ impl Request {
         req
     }
 
+    // What a `HEAD` request asks for, the same request made with `GET`
+    pub(crate) fn head_as_get(&self) -> Request {
+        Request {
+            method: Method::Get,
+            target: self.target.clone(),
+            uri: self.uri.clone(),
+            version: self.version,
+            headers: self.headers.clone(),
+            body: Vec::new(),
+        }
+    }
+
     pub fn header(mut self, header: (&str, &str)) -> Request {
-        self.headers
-            .insert(header.0.to_string(), header.1.to_string());
+        self.headers.insert(header.0, header.1);
         self
     }
 
-    pub fn body(mut self, body: &str, mime: mime::Mime) -> Request {
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B, mime: mime::Mime) -> Request {
+        let body = body.into();
         self = self.header(("Content-Length", &body.len().to_string()));
         self = self.header(("Content-Type", mime.essence_str()));
-        self.body = body.to_string();
+        self.body = body;
         self
     }
+
+    /// The body as text, if it is valid UTF-8.
+    pub fn text(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(&self.body)
+    }
+
+    /// Looks up a header value, ignoring the case of the header name.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// The URI the request is for, like `http://example.com/hello?x=1`.
+    ///
+    /// Requests in absolute form carry it in full. For the other forms it is
+    /// put together from `scheme`, which depends on the connection, the
+    /// `Host` header and the request-target. HTTP/1.0 requests may come
+    /// without a `Host` header, in which case there is no effective URI.
+    pub fn effective_uri(&self, scheme: &str) -> Result<String, Error> {
+        let host = || {
+            self.host()?
+                .ok_or_else(|| Error::from(ErrorKind::MissingHost))
+        };
+
+        let (scheme, authority, uri) = match &self.target {
+            RequestTarget::Absolute { scheme, authority } => {
+                (scheme.as_str(), authority.clone(), &self.uri)
+            }
+            RequestTarget::Origin => (scheme, host()?, &self.uri),
+            RequestTarget::Authority(authority) => (scheme, authority.clone(), &self.uri),
+            RequestTarget::Asterisk => (scheme, host()?, &self.uri),
+        };
+
+        Ok(format!("{}://{}{}", scheme, authority, uri))
+    }
+
+    /// Whether the client wants the connection to stay open after this request.
+    /// HTTP/1.0 clients have to ask for it.
+    pub fn keep_alive(&self) -> bool {
+        match self.version {
+            Version::OneDotZero => {
+                has_connection_option(&self.headers, "keep-alive")
+                    && !has_connection_option(&self.headers, "close")
+            }
+            Version::OneDotOne => !has_connection_option(&self.headers, "close"),
+        }
+    }
+
+    /// Whether the client waits for `100 Continue` before sending the body.
+    /// HTTP/1.0 clients can't know the interim response.
+    pub fn expects_continue(&self) -> bool {
+        self.version == Version::OneDotOne
+            && self
+                .headers
+                .get("Expect")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("100-continue"))
+    }
 }
 
-// Enables custom formatting of Header HashMap
-struct HeadersDisplay<'a>(&'a HashMap<String, String>);
+impl TypedHeaders for Request {
+    fn header_map(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    fn header_map_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+}
+
+// Options may be spread over several Connection fields
+fn has_connection_option(headers: &HeaderMap, option: &str) -> bool {
+    headers
+        .get_all("Connection")
+        .flat_map(|value| value.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case(option))
+}
+
+/// The body of a response, either held in memory or read from its source
+/// while the response is being sent.
+pub enum Body {
+    Bytes(Vec<u8>),
+    Stream {
+        reader: Box<dyn Read + Send>,
+        /// Streams of unknown length are sent with chunked encoding.
+        length: Option<u64>,
+    },
+}
+
+impl Body {
+    pub fn stream<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> Body {
+        Body::Stream {
+            reader: Box::new(reader),
+            length,
+        }
+    }
+
+    /// The length of the body in bytes, if it is known up front.
+    pub fn length(&self) -> Option<u64> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream { length, .. } => *length,
+        }
+    }
 
-impl<'a> fmt::Display for HeadersDisplay<'a> {
+    /// The contents of the body, unless it is streamed.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Stream { .. } => None,
+        }
+    }
+
+    fn write_to<W: Write>(self, writer: &mut W, chunked: bool) -> io::Result<()> {
+        match self {
+            Body::Bytes(bytes) => writer.write_all(&bytes),
+            Body::Stream {
+                reader,
+                length: Some(length),
+            } => {
+                let copied = io::copy(&mut reader.take(length), writer)?;
+
+                // The Content-Length header has already been sent, so a
+                // source that ends early can only be reported by closing
+                if copied < length {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                }
+
+                Ok(())
+            }
+            Body::Stream {
+                mut reader,
+                length: None,
+            } if chunked => write_chunked(&mut reader, writer),
+            Body::Stream { mut reader, .. } => io::copy(&mut reader, writer).map(|_| ()),
+        }
+    }
+}
+
+fn write_chunked<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<()> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        write!(writer, "{:X}\r\n", read)?;
+        writer.write_all(&chunk[..read])?;
+        writer.write_all(b"\r\n")?;
+    }
+
+    writer.write_all(b"0\r\n\r\n")
+}
+
+impl Default for Body {
+    fn default() -> Body {
+        Body::Bytes(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Body {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Body {
+        Body::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<String> for Body {
+    fn from(string: String) -> Body {
+        Body::Bytes(string.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(string: &str) -> Body {
+        Body::Bytes(string.as_bytes().to_vec())
+    }
+}
+
+impl fmt::Debug for Body {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for header in self.0 {
-            write!(f, "{}: {}\r\n", header.0, header.1)?;
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Body::Stream { length, .. } => f
+                .debug_struct("Stream")
+                .field("length", length)
+                .finish_non_exhaustive(),
         }
+    }
+}
 
-        Ok(())
+// Streams can't be compared without consuming them, so they never are equal
+impl PartialEq for Body {
+    fn eq(&self, other: &Body) -> bool {
+        match (self, other) {
+            (Body::Bytes(a), Body::Bytes(b)) => a == b,
+            _ => false,
+        }
     }
 }
 
@@ -224,88 +411,508 @@ impl<'a> fmt::Display for HeadersDisplay<'a> {
 pub struct Response {
     pub version: Version,
     pub status: Status,
-    pub headers: HashMap<String, String>,
-    pub body: String,
-}
-
-impl fmt::Display for Response {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} {}\r\n{}\r\n{}",
-            self.version,
-            self.status,
-            HeadersDisplay(&self.headers),
-            self.body
-        )
-    }
+    /// Sent instead of the status' usual reason phrase.
+    pub reason: Option<String>,
+    pub headers: HeaderMap,
+    pub body: Body,
+    // Set for answers to `HEAD`, whose headers describe a body that isn't
+    // sent
+    body_omitted: bool,
 }
 
 impl Response {
     pub fn new(status: Status) -> Response {
         Response {
             version: Version::OneDotOne,
-            status: status,
-            headers: HashMap::new(),
-            body: "".to_string(),
+            status,
+            reason: None,
+            headers: HeaderMap::new(),
+            body: Body::default(),
+            body_omitted: false,
         }
     }
 
     pub fn header(mut self, header: (&str, &str)) -> Response {
-        self.headers
-            .insert(header.0.to_string(), header.1.to_string());
+        self.headers.insert(header.0, header.1);
         self
     }
 
-    pub fn body(mut self, body: &str, mime: mime::Mime) -> Response {
-        self = self.header(("Content-Length", &body.len().to_string()));
+    /// Replaces the reason phrase of the status line. Control characters are
+    /// left out, since they could end the line early.
+    pub fn reason(mut self, reason: &str) -> Response {
+        let reason = reason
+            .chars()
+            .filter(|c| *c == '\t' || !c.is_control())
+            .collect();
+        self.reason = Some(reason);
+        self
+    }
+
+    /// Sets the body along with the headers describing it. Bodies of unknown
+    /// length are sent with chunked encoding.
+    pub fn body<B: Into<Body>>(mut self, body: B, mime: mime::Mime) -> Response {
+        let body = body.into();
+
+        // A body set earlier may have been framed the other way
+        match body.length() {
+            Some(length) => {
+                self.headers.remove("Transfer-Encoding");
+                self.headers.insert("Content-Length", length.to_string());
+            }
+            None => {
+                self.headers.remove("Content-Length");
+                self.headers.insert("Transfer-Encoding", "chunked");
+            }
+        }
         self = self.header(("Content-Type", mime.essence_str()));
-        self.body = body.to_string();
+        self.body = body;
         self
     }
+
+    /// The body as text, if it is valid UTF-8. Streamed bodies aren't read
+    /// until the response is sent, so they are empty here.
+    pub fn text(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(self.body.as_bytes().unwrap_or_default())
+    }
+
+    /// Looks up a header value, ignoring the case of the header name.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Whether the connection may stay open after this response was sent.
+    pub fn keep_alive(&self) -> bool {
+        !has_connection_option(&self.headers, "close") && !self.ends_with_close()
+    }
+
+    // Lets clients find the end of a response without waiting for the
+    // connection to close. Statuses that can't have a body don't get one.
+    fn add_content_length(&mut self) {
+        if let Body::Bytes(bytes) = &self.body {
+            if self.status.allows_body() && !self.headers.contains_key("Content-Length") {
+                self.headers
+                    .insert("Content-Length", bytes.len().to_string());
+            }
+        }
+    }
+
+    // HTTP/1.0 has no chunked encoding, so the end of a body of unknown
+    // length can only be told by closing the connection
+    fn ends_with_close(&self) -> bool {
+        self.version == Version::OneDotZero && self.body.length().is_none()
+    }
+}
+
+impl TypedHeaders for Response {
+    fn header_map(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    fn header_map_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
 }
 
 impl Response {
     pub fn class(&self) -> ResponseClass {
         ResponseClass::new(&self.status)
     }
-}
 
-impl Request {
-    pub fn parse(string: &str) -> Result<Self, Error> {
-        let mut sections = string.splitn(2, "\r\n\r\n");
+    /// Serializes the status line, headers and body to `writer`, reading
+    /// streamed bodies as they are written. The server adds `Date` and
+    /// `Server` headers when it sends a response, this only writes the
+    /// headers that were set.
+    pub fn write_to<W: Write>(mut self, writer: &mut W) -> io::Result<()> {
+        // A body would be taken for the start of the next response
+        if !self.status.allows_body() {
+            self.body = Body::default();
+            self.headers.remove("Content-Length");
+            self.headers.remove("Transfer-Encoding");
+        }
 
-        let header = sections.next().ok_or(Error::MalformedRequest)?; // header missing
-        let body = sections.next().ok_or(Error::MalformedRequest)?.to_string(); // body missing
+        if !self.body_omitted {
+            self.add_content_length();
+        }
 
-        let mut lines = header.split_terminator("\r\n");
-        let req_line = lines.next().ok_or(Error::MalformedRequest)?; // no request line
+        let chunked = !self.ends_with_close();
+        if !chunked {
+            self.headers.remove("Transfer-Encoding");
+        }
 
-        let mut req_line_tokens = req_line.split(' ');
+        let reason = match &self.reason {
+            Some(reason) => reason,
+            None => self.status.canonical_reason().unwrap_or_default(),
+        };
 
-        let method = Method::parse(req_line_tokens.next().ok_or(Error::MalformedRequest)?)?; // no method
-        let uri = Uri::parse(req_line_tokens.next().ok_or(Error::MalformedRequest)?)?; // no uri
-        let version = Version::parse(req_line_tokens.next().ok_or(Error::MalformedRequest)?)?; // no version
+        write!(
+            writer,
+            "{} {} {}\r\n{}\r\n",
+            self.version,
+            self.status.as_u16(),
+            reason,
+            self.headers
+        )?;
+        self.body.write_to(writer, chunked)
+    }
+
+    /// Drops the body while keeping the headers that describe it, the way
+    /// responses to `HEAD` requests are sent. Streamed bodies are never read.
+    pub fn without_body(mut self) -> Response {
+        self.add_content_length();
+
+        if self.ends_with_close() {
+            self.headers.remove("Transfer-Encoding");
+        }
+
+        self.body = Body::default();
+        self.body_omitted = true;
+        self
+    }
+
+    pub fn to_bytes(self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Request {
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let header_end = find(bytes, HEADER_TERMINATOR).ok_or(ErrorKind::Incomplete)?; // body missing
+        let header_length = header_end + HEADER_TERMINATOR.len();
+
+        let mut request = Self::parse_head(&bytes[..header_end])?;
+        let body = &bytes[header_length..];
+
+        match request.framing()? {
+            // Anything after the body belongs to the next request
+            Framing::Length(length) => {
+                let body = body.get(..length).ok_or(ErrorKind::Incomplete)?; // body cut short
+                request.body = body.to_vec();
+            }
+            Framing::Chunked => {
+                let mut decoder = ChunkedDecoder::new();
+
+                let complete = decoder.decode(body).map_err(|e| e.shift(header_length))?;
+
+                if !complete {
+                    return Err(ErrorKind::Incomplete.into()); // last chunk missing
+                }
+
+                decoder.finish(&mut request);
+            }
+        }
 
-        let headers_str: Vec<&str> = lines.collect();
+        Ok(request)
+    }
 
-        let mut headers = HashMap::new();
+    /// Parses the request line and headers, leaving the body empty. The
+    /// input ends before the empty line separating the header from the body.
+    pub(crate) fn parse_head(bytes: &[u8]) -> Result<Self, Error> {
+        // Errors point at the byte where the rest of the input starts
+        let at = |kind, rest: &[u8]| Error::at(kind, bytes.len() - rest.len());
+
+        let (rest, method) =
+            grammar::method(bytes).map_err(|rest| at(ErrorKind::InvalidMethod, rest))?;
+        let (target_start, _) =
+            grammar::sp(rest).map_err(|rest| at(ErrorKind::InvalidRequestLine, rest))?;
+        let (rest, target) = grammar::request_target(target_start)
+            .map_err(|rest| at(ErrorKind::InvalidRequestTarget, rest))?;
+        let (version_start, _) =
+            grammar::sp(rest).map_err(|rest| at(ErrorKind::InvalidRequestLine, rest))?;
+        let (mut rest, version) = grammar::http_version(version_start)
+            .map_err(|rest| at(ErrorKind::InvalidVersion, rest))?;
+
+        let method = Method::parse(as_str(method)).map_err(|e| Error::at(e.kind(), 0))?;
+        let (target, uri) = RequestTarget::parse(as_str(target), &method)
+            .map_err(|e| at(e.kind(), target_start))?;
+        let version = Version::parse(as_str(version)).map_err(|e| at(e.kind(), version_start))?;
+
+        let mut headers = HeaderMap::new();
+        let mut line_error = ErrorKind::InvalidRequestLine;
+
+        while !rest.is_empty() {
+            let (line, _) = grammar::crlf(rest).map_err(|rest| at(line_error, rest))?;
+
+            let (next, (name, value)) = grammar::field_line(line).map_err(|failed| {
+                // Only the name and the colon can fail to match
+                match failed.len() == line.len() {
+                    true => at(ErrorKind::InvalidHeaderName, failed),
+                    false => at(ErrorKind::MissingColon, failed),
+                }
+            })?;
+
+            // Header fields may carry bytes that aren't valid UTF-8, which we
+            // can't hold
+            let value = str::from_utf8(value)
+                .map_err(|e| at(ErrorKind::InvalidHeaderValue, &value[e.valid_up_to()..]))?;
+
+            headers.append(as_str(name), value);
+            line_error = ErrorKind::InvalidHeaderValue;
+            rest = next;
+        }
 
-        for line in headers_str {
-            let mut split_header = line.split(':');
-            let header_key = split_header.next().ok_or(Error::MalformedRequest)?; // no header key
-            let header_value = split_header.next().ok_or(Error::MalformedRequest)?.trim(); // no header value
-            headers.insert(header_key.to_string(), header_value.to_string());
+        // HTTP/1.1 clients have to name the host, even when the target
+        // already does
+        if version == Version::OneDotOne {
+            match headers.get_all("Host").count() {
+                0 => return Err(ErrorKind::MissingHost.into()),
+                1 => {}
+                _ => return Err(ErrorKind::DuplicateHost.into()),
+            }
         }
 
         Ok(Request {
             method,
+            target,
             uri,
             version,
             headers,
-            body,
+            body: Vec::new(),
         })
     }
+
+    /// Works out how the end of the body is found. Requests carrying both
+    /// `Content-Length` and `Transfer-Encoding` are rejected, since servers
+    /// disagreeing about which one wins makes request smuggling possible.
+    pub(crate) fn framing(&self) -> Result<Framing, Error> {
+        // Repeated fields could otherwise be read differently by a proxy
+        let mut lengths = self.headers.get_all("Content-Length");
+        let first_length = lengths.next();
+        if lengths.any(|length| Some(length) != first_length) {
+            return Err(ErrorKind::InvalidContentLength.into());
+        }
+        // HTTP/1.0 has no transfer codings
+        let encodings = self.headers.get_all("Transfer-Encoding").count();
+        if encodings > 1 || (encodings > 0 && self.version == Version::OneDotZero) {
+            return Err(ErrorKind::UnsupportedTransferEncoding.into());
+        }
+
+        let transfer_encoding = self.header_value("Transfer-Encoding");
+        let content_length = self.header_value("Content-Length");
+
+        match (transfer_encoding, content_length) {
+            (Some(_), Some(_)) => Err(ErrorKind::ConflictingFraming.into()),
+            // Chunked is the only coding we can decode
+            (Some(encoding), None) if encoding.trim().eq_ignore_ascii_case("chunked") => {
+                Ok(Framing::Chunked)
+            }
+            (Some(_), None) => Err(ErrorKind::UnsupportedTransferEncoding.into()),
+            (None, Some(length))
+                if !length.is_empty() && length.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                length
+                    .parse()
+                    .map(Framing::Length)
+                    .or(Err(ErrorKind::InvalidContentLength.into()))
+            }
+            (None, Some(_)) => Err(ErrorKind::InvalidContentLength.into()),
+            (None, None) => Ok(Framing::Length(0)),
+        }
+    }
+}
+
+/// How the end of a request body is found.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Framing {
+    Length(usize),
+    Chunked,
+}
+
+// Trailers can't change how a request was framed or where it is routed
+const IGNORED_TRAILERS: &[&str] = &[
+    "Connection",
+    "Content-Length",
+    "Host",
+    "Trailer",
+    "Transfer-Encoding",
+];
+
+enum ChunkedState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+/// Decodes a chunked body as its bytes arrive. Every call to `decode` gets
+/// the input from the first byte the previous calls didn't consume, so the
+/// framing doesn't have to be kept around.
+pub(crate) struct ChunkedDecoder {
+    state: ChunkedState,
+    consumed: usize,
+    body: Vec<u8>,
+    trailers: Vec<(String, String)>,
+    trailer_length: usize,
+}
+
+impl ChunkedDecoder {
+    pub(crate) fn new() -> ChunkedDecoder {
+        ChunkedDecoder {
+            state: ChunkedState::Size,
+            consumed: 0,
+            body: Vec::new(),
+            trailers: Vec::new(),
+            trailer_length: 0,
+        }
+    }
+
+    /// Returns `true` once the last chunk and the trailers have been read.
+    pub(crate) fn decode(&mut self, input: &[u8]) -> Result<bool, Error> {
+        let start = self.consumed;
+
+        loop {
+            let rest = &input[self.consumed - start..];
+
+            match self.state {
+                ChunkedState::Size => {
+                    let line = match read_line(rest, MAX_CHUNK_SIZE_LINE_LENGTH) {
+                        Ok(Some(line)) => line,
+                        Ok(None) => return Ok(false),
+                        Err(()) => {
+                            return Err(Error::at(ErrorKind::InvalidChunkSize, self.consumed))
+                        }
+                    };
+                    let size =
+                        parse_chunk_size(line).map_err(|kind| Error::at(kind, self.consumed))?;
+                    self.consumed += line.len() + 2;
+
+                    self.state = match size {
+                        0 => ChunkedState::Trailers,
+                        size => ChunkedState::Data(size),
+                    };
+                }
+                ChunkedState::Data(remaining) => {
+                    if rest.is_empty() {
+                        return Ok(false);
+                    }
+
+                    let available = remaining.min(rest.len());
+                    self.body.extend_from_slice(&rest[..available]);
+                    self.consumed += available;
+
+                    self.state = match remaining - available {
+                        0 => ChunkedState::DataEnd,
+                        remaining => ChunkedState::Data(remaining),
+                    };
+                }
+                ChunkedState::DataEnd => {
+                    if rest.len() < 2 {
+                        return Ok(false);
+                    }
+
+                    if &rest[..2] != b"\r\n" {
+                        return Err(Error::at(ErrorKind::InvalidChunk, self.consumed));
+                    }
+
+                    self.consumed += 2;
+                    self.state = ChunkedState::Size;
+                }
+                ChunkedState::Trailers => {
+                    let line = match read_line(rest, MAX_TRAILER_LINE_LENGTH) {
+                        Ok(Some(line)) => line,
+                        Ok(None) => return Ok(false),
+                        Err(()) => return Err(Error::at(ErrorKind::InvalidTrailer, self.consumed)),
+                    };
+
+                    if line.is_empty() {
+                        self.consumed += 2;
+                        self.state = ChunkedState::Done;
+                        continue;
+                    }
+
+                    let trailer =
+                        parse_trailer(line).map_err(|kind| Error::at(kind, self.consumed))?;
+                    self.trailers.push(trailer);
+                    self.consumed += line.len() + 2;
+                    self.trailer_length += line.len() + 2;
+                }
+                ChunkedState::Done => return Ok(true),
+            }
+        }
+    }
+
+    /// How many bytes of the input belong to the chunked body so far, over
+    /// all calls to `decode`.
+    pub(crate) fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    pub(crate) fn body_length(&self) -> usize {
+        self.body.len()
+    }
+
+    pub(crate) fn trailer_length(&self) -> usize {
+        self.trailer_length
+    }
+
+    /// Hands the decoded body to `request` and adds the trailers to its
+    /// headers, without overriding any header that was sent up front.
+    pub(crate) fn finish(self, request: &mut Request) {
+        request.body = self.body;
+
+        for (name, value) in self.trailers {
+            let ignored = IGNORED_TRAILERS
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(&name));
+
+            if !ignored && !request.headers.contains_key(&name) {
+                request.headers.append(name, value);
+            }
+        }
+    }
+}
+
+// Leaves room for a few chunk extensions, which are otherwise ignored
+const MAX_CHUNK_SIZE_LINE_LENGTH: usize = 256;
+const MAX_TRAILER_LINE_LENGTH: usize = 4096;
+
+// Lines that don't end within `limit` bytes are an error
+fn read_line(input: &[u8], limit: usize) -> Result<Option<&[u8]>, ()> {
+    let searched = &input[..input.len().min(limit + 2)];
+
+    match find(searched, b"\r\n") {
+        Some(end) => Ok(Some(&input[..end])),
+        None if input.len() >= limit + 2 => Err(()),
+        None => Ok(None),
+    }
+}
+
+fn parse_chunk_size(line: &[u8]) -> Result<usize, ErrorKind> {
+    // Chunk extensions are allowed but carry nothing we act on
+    let size = line.split(|&byte| byte == b';').next().unwrap_or(line);
+    let size = str::from_utf8(size)
+        .or(Err(ErrorKind::InvalidChunkSize))?
+        .trim_end_matches([' ', '\t']);
+
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ErrorKind::InvalidChunkSize);
+    }
+
+    usize::from_str_radix(size, 16).or(Err(ErrorKind::InvalidChunkSize))
+}
+
+// Trailers follow the same grammar as header fields
+fn parse_trailer(line: &[u8]) -> Result<(String, String), ErrorKind> {
+    match grammar::field_line(line) {
+        Ok(([], (name, value))) => {
+            let value = str::from_utf8(value).or(Err(ErrorKind::InvalidTrailer))?;
+            Ok((as_str(name).to_string(), value.to_string()))
+        }
+        _ => Err(ErrorKind::InvalidTrailer),
+    }
+}
+
+// Only for input the grammar has limited to ASCII
+fn as_str(bytes: &[u8]) -> &str {
+    str::from_utf8(bytes).unwrap_or_default()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
 }
 
 #[cfg(test)]
@@ -314,11 +921,12 @@ mod tests {
     extern crate test;
     use test::Bencher;
 
-    fn create_dummy_request_string() -> &'static str {
-        "GET /hello.txt HTTP/1.1\r\n\
+    fn create_dummy_request_string() -> &'static [u8] {
+        b"GET /hello.txt HTTP/1.1\r\n\
         User-Agent: curl/7.16.3 libcurl/7.16.3 OpenSSL/0.9.7l zlib/1.2.3\r\n\
         Host: www.example.com\r\n\
         Accept-Language: en, mi\r\n\
+        Content-Length: 36\r\n\
         \r\n\
         This is the body \r\nof the request.\r\n"
     }
@@ -332,17 +940,290 @@ mod tests {
         assert_eq!(request.method, Method::Get);
         assert_eq!(request.uri, Uri::new("/hello.txt"));
         assert_eq!(request.version, Version::OneDotOne);
-        let mut headers = HashMap::new();
-        headers.insert(
-            "User-Agent".to_string(),
-            "curl/7.16.3 libcurl/7.16.3 OpenSSL/0.9.7l zlib/1.2.3".to_string(),
+        assert_eq!(
+            request.headers.iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "User-Agent",
+                    "curl/7.16.3 libcurl/7.16.3 OpenSSL/0.9.7l zlib/1.2.3"
+                ),
+                ("Host", "www.example.com"),
+                ("Accept-Language", "en, mi"),
+                ("Content-Length", "36"),
+            ]
         );
-        headers.insert("Host".to_string(), "www.example.com".to_string());
-        headers.insert("Accept-Language".to_string(), "en, mi".to_string());
-        assert_eq!(request.headers, headers);
+        assert_eq!(request.body, b"This is the body \r\nof the request.\r\n");
+    }
+
+    #[test]
+    fn request_parsing_reads_exactly_content_length() {
+        let request = Request::parse(
+            b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\r\n\
+            abcdefGET / HTTP/1.1\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.body, b"abc");
+
+        let error =
+            Request::parse(b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 30\r\n\r\nab")
+                .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Incomplete);
+    }
+
+    #[test]
+    fn request_parsing_keeps_binary_body() {
+        let mut http_req = b"POST /upload HTTP/1.1\r\n\
+            Host: example.com\r\nContent-Length: 4\r\n\r\n"
+            .to_vec();
+        http_req.extend_from_slice(&[0xff, 0x00, 0xfe, 0x0a]);
+
+        let request = Request::parse(&http_req).unwrap();
+
+        assert_eq!(request.body, vec![0xff, 0x00, 0xfe, 0x0a]);
+        assert!(request.text().is_err());
+    }
+
+    #[test]
+    fn request_parsing_keeps_colons_in_header_values() {
+        let http_req = b"GET / HTTP/1.1\r\nHost: example.com:8080\r\nReferer: http://a/\r\n\r\n";
+
+        let request = Request::parse(http_req).unwrap();
+
+        assert_eq!(request.header_value("Host"), Some("example.com:8080"));
+        assert_eq!(request.header_value("Referer"), Some("http://a/"));
+    }
+
+    fn parse_error(http_req: &[u8]) -> (ErrorKind, Option<usize>) {
+        let error = Request::parse(http_req).unwrap_err();
+        (error.kind(), error.offset())
+    }
+
+    #[test]
+    fn request_parsing_reports_error_location() {
+        assert_eq!(
+            parse_error(b"G{T / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            (ErrorKind::InvalidMethod, Some(1))
+        );
+        assert_eq!(
+            parse_error(b"G\xc3\xa9T / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            (ErrorKind::InvalidMethod, Some(1))
+        );
+        assert_eq!(
+            parse_error(b"GET /\xff HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            (ErrorKind::InvalidRequestTarget, Some(5))
+        );
+        assert_eq!(
+            parse_error(b"GET /  HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            (ErrorKind::InvalidVersion, Some(6))
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/2.0\r\n\r\n"),
+            (ErrorKind::UnsupportedVersion, Some(6))
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1 \r\n\r\n"),
+            (ErrorKind::InvalidRequestLine, Some(14))
+        );
+    }
+
+    #[test]
+    fn request_parsing_reports_header_error_location() {
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\nHost : example.com\r\n\r\n"),
+            (ErrorKind::MissingColon, Some(20))
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\nHost: example.com\r\nX-Folded: a\r\n b\r\n\r\n"),
+            (ErrorKind::InvalidHeaderName, Some(48))
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\nHost: example.com\r\nX-Bad: a\x01b\r\n\r\n"),
+            (ErrorKind::InvalidHeaderValue, Some(43))
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\nHost: example.com\r\nX-Bad: caf\xe9\r\n\r\n"),
+            (ErrorKind::InvalidHeaderValue, Some(45))
+        );
+    }
+
+    #[test]
+    fn request_parsing_requires_one_host_for_http_1_1() {
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\n\r\n"),
+            (ErrorKind::MissingHost, None)
+        );
+        assert_eq!(
+            parse_error(b"GET / HTTP/1.1\r\nHost: a.example\r\nHost: b.example\r\n\r\n"),
+            (ErrorKind::DuplicateHost, None)
+        );
+        assert_eq!(
+            parse_error(b"GET http://a.example/ HTTP/1.1\r\n\r\n"),
+            (ErrorKind::MissingHost, None)
+        );
+        assert!(Request::parse(b"GET / HTTP/1.0\r\n\r\n").is_ok());
+    }
+
+    #[test]
+    fn request_parsing_reports_chunk_error_location() {
+        assert_eq!(
+            parse_error(
+                b"POST / HTTP/1.1\r\n\
+                Host: example.com\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabcd\r\n"
+            ),
+            (ErrorKind::InvalidChunk, Some(72))
+        );
+        assert_eq!(
+            parse_error(
+                b"POST / HTTP/1.1\r\n\
+                Host: example.com\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nab"
+            ),
+            (ErrorKind::Incomplete, None)
+        );
+    }
+
+    #[test]
+    fn request_parsing_decodes_chunked_body() {
+        let http_req = b"POST /upload HTTP/1.1\r\nHost: example.com\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            5;name=value\r\nHello\r\n\
+            8\r\n, world!\r\n\
+            0\r\n\
+            Checksum: abc\r\n\
+            Content-Length: 100\r\n\
+            \r\n";
+
+        let request = Request::parse(http_req).unwrap();
+
+        assert_eq!(request.body, b"Hello, world!");
+        assert_eq!(request.header_value("Checksum"), Some("abc"));
+        assert_eq!(request.header_value("Content-Length"), None);
+    }
+
+    #[test]
+    fn request_parsing_keeps_repeated_headers() {
+        let http_req = b"GET / HTTP/1.1\r\n\
+            Host: example.com\r\nAccept: text/html\r\naccept: */*\r\n\r\n";
+
+        let request = Request::parse(http_req).unwrap();
+
+        assert_eq!(
+            request.headers.get_all("Accept").collect::<Vec<_>>(),
+            vec!["text/html", "*/*"]
+        );
+    }
+
+    #[test]
+    fn request_parsing_rejects_content_length_with_transfer_encoding() {
+        let http_req = b"POST / HTTP/1.1\r\nHost: example.com\r\n\
+            Content-Length: 5\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            0\r\n\r\n";
+
+        assert_eq!(parse_error(http_req), (ErrorKind::ConflictingFraming, None));
+    }
+
+    #[test]
+    fn request_framing() {
+        let framing = |header: (&str, &str)| Request::post("/").header(header).framing();
+
+        assert_eq!(Request::get("/").framing(), Ok(Framing::Length(0)));
+        assert_eq!(framing(("Content-Length", "42")), Ok(Framing::Length(42)));
+        assert_eq!(
+            framing(("Content-Length", "+42")),
+            Err(ErrorKind::InvalidContentLength.into())
+        );
+        assert_eq!(
+            framing(("Transfer-Encoding", "Chunked")),
+            Ok(Framing::Chunked)
+        );
+        assert_eq!(
+            framing(("Transfer-Encoding", "gzip, chunked")),
+            Err(ErrorKind::UnsupportedTransferEncoding.into())
+        );
+    }
+
+    #[test]
+    fn request_framing_with_repeated_fields() {
+        let framing = |name: &str, values: &[&str]| {
+            let mut request = Request::post("/");
+            for value in values {
+                request.headers.append(name, *value);
+            }
+            request.framing()
+        };
+
+        assert_eq!(
+            framing("Content-Length", &["5", "5"]),
+            Ok(Framing::Length(5))
+        );
+        assert_eq!(
+            framing("Content-Length", &["5", "6"]),
+            Err(ErrorKind::InvalidContentLength.into())
+        );
+        assert_eq!(
+            framing("Transfer-Encoding", &["chunked", "chunked"]),
+            Err(ErrorKind::UnsupportedTransferEncoding.into())
+        );
+    }
+
+    #[test]
+    fn chunked_decoder_resumes_with_more_input() {
+        let input = b"3\r\nabc\r\n4\r\ndefg\r\n0\r\n\r\nGET";
+        let mut decoder = ChunkedDecoder::new();
+
+        // Only the bytes the decoder couldn't use yet are passed again
+        let mut pending = Vec::new();
+        for byte in &input[..input.len() - 4] {
+            pending.push(*byte);
+            let consumed = decoder.consumed();
+            assert_eq!(decoder.decode(&pending), Ok(false));
+            pending.drain(..decoder.consumed() - consumed);
+            assert!(pending.len() <= 2);
+        }
+        pending.extend_from_slice(&input[input.len() - 4..]);
+        assert_eq!(decoder.decode(&pending), Ok(true));
+        assert_eq!(decoder.consumed(), input.len() - 3);
+
+        let mut request = Request::post("/");
+        decoder.finish(&mut request);
+        assert_eq!(request.body, b"abcdefg");
+    }
+
+    #[test]
+    fn chunked_decoder_rejects_malformed_chunks() {
+        let decode = |input: &[u8]| ChunkedDecoder::new().decode(input);
+
+        assert_eq!(
+            decode(b"x\r\n"),
+            Err(Error::at(ErrorKind::InvalidChunkSize, 0))
+        );
+        assert_eq!(
+            decode(b"\r\n"),
+            Err(Error::at(ErrorKind::InvalidChunkSize, 0))
+        );
+        assert_eq!(
+            decode(b"3\r\nabcd\r\n"),
+            Err(Error::at(ErrorKind::InvalidChunk, 6))
+        );
+        assert_eq!(
+            decode(b"ffffffffffffffffff\r\n"),
+            Err(Error::at(ErrorKind::InvalidChunkSize, 0))
+        );
+        assert_eq!(
+            decode(b"1\r\na\r\n0\r\nno colon\r\n\r\n"),
+            Err(Error::at(ErrorKind::InvalidTrailer, 9))
+        );
+
+        let long_extension = format!("1;{}", "a".repeat(MAX_CHUNK_SIZE_LINE_LENGTH));
         assert_eq!(
-            request.body,
-            "This is the body \r\nof the request.\r\n".to_string()
+            decode(long_extension.as_bytes()),
+            Err(Error::at(ErrorKind::InvalidChunkSize, 0))
+        );
+        assert_eq!(
+            decode(format!("{}\r\na\r\n", long_extension).as_bytes()),
+            Err(Error::at(ErrorKind::InvalidChunkSize, 0))
         );
     }
 
@@ -363,15 +1244,44 @@ mod tests {
         assert_eq!(Method::parse("CONNECT"), Ok(Method::Connect));
         assert_eq!(Method::parse("OPTIONS"), Ok(Method::Options));
         assert_eq!(Method::parse("TRACE"), Ok(Method::Trace));
+        assert_eq!(Method::parse("PATCH"), Ok(Method::Patch));
+
+        assert_eq!(
+            Method::parse("PROPFIND"),
+            Ok(Method::Extension("PROPFIND".to_string()))
+        );
+        assert_eq!(
+            Method::parse("get"),
+            Ok(Method::Extension("get".to_string()))
+        );
+        assert_eq!(Method::parse("GE T"), Err(ErrorKind::InvalidMethod.into()));
+        assert_eq!(Method::parse(""), Err(ErrorKind::InvalidMethod.into()));
+    }
 
-        assert_eq!(Method::parse("SOMETHING"), Err(Error::UnknownMethod));
+    #[test]
+    fn method_properties() {
+        assert!(Method::Get.is_safe());
+        assert!(Method::Get.is_idempotent());
+        assert!(!Method::Put.is_safe());
+        assert!(Method::Put.is_idempotent());
+        assert!(!Method::Post.is_idempotent());
+        assert!(!Method::Patch.is_idempotent());
+
+        let extension = Method::Extension("PROPFIND".to_string());
+        assert!(!extension.is_safe());
+        assert!(!extension.is_idempotent());
+        assert_eq!(extension.to_string(), "PROPFIND");
     }
 
     #[test]
     fn version_parsing() {
+        assert_eq!(Version::parse("HTTP/1.0"), Ok(Version::OneDotZero));
         assert_eq!(Version::parse("HTTP/1.1"), Ok(Version::OneDotOne));
 
-        assert_eq!(Version::parse("XYZ/1.0"), Err(Error::UnsupportedVersion));
+        assert_eq!(
+            Version::parse("XYZ/1.0"),
+            Err(ErrorKind::UnsupportedVersion.into())
+        );
     }
 
     #[test]
@@ -385,18 +1295,120 @@ mod tests {
         assert_eq!(post_req.uri.path, "/test");
 
         let post_req = post_req.body("Hello, world!", mime::TEXT_PLAIN);
-        assert_eq!(post_req.body, "Hello, world!".to_string());
+        assert_eq!(post_req.text(), Ok("Hello, world!"));
         assert_eq!(post_req.headers.get("Content-Length").unwrap(), "13");
         assert_eq!(post_req.headers.get("Content-Type").unwrap(), "text/plain");
     }
 
+    #[test]
+    fn request_parsing_reads_target_form() {
+        let request = Request::parse(b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert_eq!(request.target, RequestTarget::Asterisk);
+
+        assert_eq!(
+            parse_error(b"GET * HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            (ErrorKind::InvalidRequestTarget, Some(4))
+        );
+    }
+
+    #[test]
+    fn request_effective_uri() {
+        let effective_uri =
+            |http_req: &[u8]| Request::parse(http_req).unwrap().effective_uri("http");
+
+        assert_eq!(
+            effective_uri(b"GET /a%20b?x=1 HTTP/1.1\r\nHost: example.com:8080\r\n\r\n"),
+            Ok("http://example.com:8080/a%20b?x=1".to_string())
+        );
+        assert_eq!(
+            effective_uri(b"GET https://example.org/ HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Ok("https://example.org/".to_string())
+        );
+        assert_eq!(
+            effective_uri(b"CONNECT example.org:443 HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Ok("http://example.org:443".to_string())
+        );
+        assert_eq!(
+            effective_uri(b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Ok("http://example.com".to_string())
+        );
+        assert_eq!(
+            effective_uri(b"GET / HTTP/1.0\r\n\r\n"),
+            Err(ErrorKind::MissingHost.into())
+        );
+    }
+
+    #[test]
+    fn request_keep_alive() {
+        assert!(Request::get("/").keep_alive());
+        assert!(Request::get("/")
+            .header(("Connection", "keep-alive"))
+            .keep_alive());
+        assert!(!Request::get("/")
+            .header(("Connection", "close"))
+            .keep_alive());
+        assert!(!Request::get("/")
+            .header(("connection", "Upgrade, Close"))
+            .keep_alive());
+
+        let mut request = Request::get("/").header(("Connection", "Upgrade"));
+        request.headers.append("Connection", "close");
+        assert!(!request.keep_alive());
+    }
+
+    #[test]
+    fn request_keep_alive_http_1_0() {
+        let keep_alive = |http_req: &[u8]| Request::parse(http_req).unwrap().keep_alive();
+
+        assert!(!keep_alive(b"GET / HTTP/1.0\r\n\r\n"));
+        assert!(keep_alive(
+            b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n"
+        ));
+        assert!(!keep_alive(
+            b"GET / HTTP/1.0\r\nConnection: keep-alive, close\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn request_framing_http_1_0() {
+        let http_req = b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+
+        assert_eq!(
+            parse_error(http_req),
+            (ErrorKind::UnsupportedTransferEncoding, None)
+        );
+    }
+
+    #[test]
+    fn request_typed_headers() {
+        let request = Request::post("/")
+            .header(("Host", "example.com"))
+            .body("{}", mime::APPLICATION_JSON);
+
+        assert_eq!(request.content_length(), Ok(Some(2)));
+        assert_eq!(request.content_type(), Ok(Some(mime::APPLICATION_JSON)));
+        assert_eq!(request.host().unwrap().unwrap().name, "example.com");
+        assert_eq!(request.range(), Ok(None));
+    }
+
+    #[test]
+    fn response_typed_headers() {
+        let mut response = Response::new(Status::Ok);
+        response.set_location("/elsewhere");
+        response.set_cache_control(&CacheControl::new().directive("max-age", Some("60")));
+
+        assert_eq!(response.header_value("Location"), Some("/elsewhere"));
+        assert_eq!(response.header_value("Cache-Control"), Some("max-age=60"));
+        assert_eq!(response.content_type(), Ok(None));
+    }
+
     #[test]
     fn response_building() {
         let response = Response::new(Status::Ok);
         assert_eq!(response.status, Status::Ok);
 
         let response = response.body("Hello, world!", mime::TEXT_PLAIN);
-        assert_eq!(response.body, "Hello, world!".to_string());
+        assert_eq!(response.text(), Ok("Hello, world!"));
         assert_eq!(response.headers.get("Content-Length").unwrap(), "13");
         assert_eq!(response.headers.get("Content-Type").unwrap(), "text/plain");
 
@@ -410,15 +1422,168 @@ mod tests {
             .body("Hello, world!", mime::TEXT_PLAIN)
             .header(("Hello", "World!"));
 
-        let response_string = format!("{}", response);
+        let response_bytes = response.to_bytes().unwrap();
+        let response_string = str::from_utf8(&response_bytes).unwrap();
+
+        assert_eq!(
+            response_string,
+            "HTTP/1.1 200 OK\r\n\
+            Content-Length: 13\r\n\
+            Content-Type: text/plain\r\n\
+            Hello: World!\r\n\
+            \r\n\
+            Hello, world!"
+        );
+    }
+
+    #[test]
+    fn response_formatting_keeps_repeated_headers() {
+        let mut response = Response::new(Status::Ok);
+        response.headers.append("Set-Cookie", "a=1");
+        response.headers.append("Set-Cookie", "b=2");
+
+        let response_bytes = response.to_bytes().unwrap();
+
+        assert_eq!(
+            str::from_utf8(&response_bytes).unwrap(),
+            "HTTP/1.1 200 OK\r\n\
+            Set-Cookie: a=1\r\n\
+            Set-Cookie: b=2\r\n\
+            Content-Length: 0\r\n\
+            \r\n"
+        );
+    }
+
+    #[test]
+    fn response_serializes_binary_body() {
+        let body = vec![0x89, b'P', b'N', b'G', 0x00, 0xff];
+        let response = Response::new(Status::Ok).body(body.clone(), mime::IMAGE_PNG);
+
+        assert!(response.to_bytes().unwrap().ends_with(&body));
+    }
 
-        // This is a workaround because when the header HashMap is serialized
-        // the headers don't always end up in the same order
-        // TODO: Unfortunately this does not catch all possible errors
-        assert!(response_string.starts_with("HTTP/1.1 200 OK\r\n"));
-        assert!(response_string.contains("Content-Length: 13\r\n"));
-        assert!(response_string.contains("Content-Type: text/plain\r\n"));
-        assert!(response_string.contains("Hello: World!\r\n"));
-        assert!(response_string.ends_with("\r\nHello, world!"));
+    #[test]
+    fn response_body_replaces_framing() {
+        let body = Body::stream(&b"abc"[..], None);
+        let response = Response::new(Status::Ok)
+            .body("abc", mime::TEXT_PLAIN)
+            .body(body, mime::TEXT_PLAIN);
+        assert_eq!(response.header_value("Content-Length"), None);
+        assert_eq!(response.header_value("Transfer-Encoding"), Some("chunked"));
+
+        let response = response.body("abc", mime::TEXT_PLAIN);
+        assert_eq!(response.header_value("Content-Length"), Some("3"));
+        assert_eq!(response.header_value("Transfer-Encoding"), None);
+    }
+
+    #[test]
+    fn response_streams_body_of_known_length() {
+        let body = Body::stream(&b"Hello, world!"[..], Some(13));
+        let response = Response::new(Status::Ok).body(body, mime::TEXT_PLAIN);
+        assert_eq!(response.headers.get("Content-Length").unwrap(), "13");
+        assert_eq!(response.text(), Ok(""));
+
+        let response_bytes = response.to_bytes().unwrap();
+
+        assert!(response_bytes.ends_with(b"\r\n\r\nHello, world!"));
+    }
+
+    #[test]
+    fn response_streams_body_of_unknown_length_chunked() {
+        let body = Body::stream(&b"Hello, world!"[..], None);
+        let response = Response::new(Status::Ok).body(body, mime::TEXT_PLAIN);
+        assert_eq!(
+            response.headers.get("Transfer-Encoding").unwrap(),
+            "chunked"
+        );
+        assert!(!response.headers.contains_key("Content-Length"));
+
+        let response_bytes = response.to_bytes().unwrap();
+
+        assert!(response_bytes.ends_with(b"\r\n\r\nD\r\nHello, world!\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn response_streams_body_of_unknown_length_until_close_for_http_1_0() {
+        let body = Body::stream(&b"Hello, world!"[..], None);
+        let mut response = Response::new(Status::Ok).body(body, mime::TEXT_PLAIN);
+        response.version = Version::OneDotZero;
+        assert!(!response.keep_alive());
+
+        let response_bytes = response.to_bytes().unwrap();
+        let response_string = str::from_utf8(&response_bytes).unwrap();
+
+        assert!(response_string.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(!response_string.contains("Transfer-Encoding"));
+        assert!(response_string.ends_with("\r\n\r\nHello, world!"));
+    }
+
+    #[test]
+    fn response_fails_on_stream_shorter_than_length() {
+        let body = Body::stream(&b"Hello"[..], Some(13));
+        let response = Response::new(Status::Ok).body(body, mime::TEXT_PLAIN);
+
+        let error = response.to_bytes().unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn response_without_body_has_content_length() {
+        let response_bytes = Response::new(Status::NotFound).to_bytes().unwrap();
+        let response_string = str::from_utf8(&response_bytes).unwrap();
+
+        assert!(response_string.contains("Content-Length: 0\r\n"));
+    }
+
+    #[test]
+    fn response_without_allowed_body_has_no_content_length() {
+        for status in &[
+            Status::SwitchingProtocols,
+            Status::NoContent,
+            Status::NotModified,
+        ] {
+            let response_bytes = Response::new(*status).to_bytes().unwrap();
+            let response_string = str::from_utf8(&response_bytes).unwrap();
+
+            assert!(!response_string.contains("Content-Length"));
+        }
+    }
+
+    #[test]
+    fn response_without_body_status_drops_body() {
+        let response = Response::new(Status::NoContent).body("Hello", mime::TEXT_PLAIN);
+        assert_eq!(
+            response.to_bytes().unwrap(),
+            b"HTTP/1.1 204 No Content\r\nContent-Type: text/plain\r\n\r\n"
+        );
+
+        let body = Body::stream(&b"Hello"[..], None);
+        let response = Response::new(Status::NotModified).body(body, mime::TEXT_PLAIN);
+        assert_eq!(
+            response.to_bytes().unwrap(),
+            b"HTTP/1.1 304 Not Modified\r\nContent-Type: text/plain\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn response_formatting_uses_reason() {
+        let response = Response::new(Status::Forbidden);
+        assert!(response
+            .to_bytes()
+            .unwrap()
+            .starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+
+        let response = Response::new(Status::Ok).reason("All\r\nGood");
+        assert!(response
+            .to_bytes()
+            .unwrap()
+            .starts_with(b"HTTP/1.1 200 AllGood\r\n"));
+
+        let response = Response::new(Status::from_u16(299).unwrap());
+        assert!(response
+            .to_bytes()
+            .unwrap()
+            .starts_with(b"HTTP/1.1 299 \r\n"));
     }
 }
@@ -0,0 +1,130 @@
+use std::fmt;
+
+/// What was wrong with a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidMethod,
+    InvalidRequestTarget,
+    InvalidVersion,
+    UnsupportedVersion,
+    InvalidRequestLine,
+    InvalidHeaderName,
+    MissingColon,
+    InvalidHeaderValue,
+    MissingHost,
+    DuplicateHost,
+    HeaderTooLarge,
+    InvalidContentLength,
+    ConflictingFraming,
+    UnsupportedTransferEncoding,
+    InvalidChunkSize,
+    InvalidChunk,
+    InvalidTrailer,
+    BodyTooLarge,
+    Incomplete,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ErrorKind::InvalidMethod => "Method is not a valid token",
+            ErrorKind::InvalidRequestTarget => "Invalid request target",
+            ErrorKind::InvalidVersion => "Invalid HTTP version",
+            ErrorKind::UnsupportedVersion => "Unsupported version",
+            ErrorKind::InvalidRequestLine => "Malformed request line",
+            ErrorKind::InvalidHeaderName => "Header name is not a valid token",
+            ErrorKind::MissingColon => "Missing colon after header name",
+            ErrorKind::InvalidHeaderValue => "Invalid header value",
+            ErrorKind::MissingHost => "Missing Host header",
+            ErrorKind::DuplicateHost => "Host header sent more than once",
+            ErrorKind::HeaderTooLarge => "Header too large",
+            ErrorKind::InvalidContentLength => "Invalid Content-Length",
+            ErrorKind::ConflictingFraming => "Both Content-Length and Transfer-Encoding are set",
+            ErrorKind::UnsupportedTransferEncoding => "Unsupported Transfer-Encoding",
+            ErrorKind::InvalidChunkSize => "Invalid chunk size",
+            ErrorKind::InvalidChunk => "Chunk is longer than its size",
+            ErrorKind::InvalidTrailer => "Invalid trailer",
+            ErrorKind::BodyTooLarge => "Body too large",
+            ErrorKind::Incomplete => "Request ended early",
+        };
+
+        write!(f, "{}", kind)
+    }
+}
+
+/// An error found while parsing a request, along with the offset of the
+/// offending byte from the start of the request when it is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<usize>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error { kind, offset: None }
+    }
+
+    pub fn at(kind: ErrorKind, offset: usize) -> Error {
+        Error {
+            kind,
+            offset: Some(offset),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    // For errors found in a part of the request that doesn't start at its
+    // beginning, like the body
+    pub(crate) fn shift(mut self, by: usize) -> Error {
+        self.offset = self.offset.map(|offset| offset + by);
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at byte {}", self.kind, offset),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_display() {
+        assert_eq!(
+            Error::at(ErrorKind::MissingColon, 21).to_string(),
+            "Missing colon after header name at byte 21"
+        );
+        assert_eq!(
+            Error::new(ErrorKind::BodyTooLarge).to_string(),
+            "Body too large"
+        );
+    }
+
+    #[test]
+    fn error_shift() {
+        assert_eq!(
+            Error::at(ErrorKind::InvalidChunk, 3).shift(40).offset(),
+            Some(43)
+        );
+        assert_eq!(Error::new(ErrorKind::Incomplete).shift(40).offset(), None);
+    }
+}
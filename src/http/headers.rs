@@ -0,0 +1,232 @@
+use std::{fmt, iter::FromIterator, slice};
+
+/// Header fields of a request or response.
+///
+/// Names are compared ignoring their case, but are kept as they were given.
+/// Fields stay in the order they were added, and a name can have several
+/// values, like `Set-Cookie` usually does. CR, LF and NUL are left out of
+/// names and values, since they could end a field early.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    fields: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    /// Sets the value of `name`, replacing all values it had so far. The
+    /// field keeps its position if it was already there.
+    pub fn insert<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        let name = strip_line_breaks(name.into());
+        let value = strip_line_breaks(value.into());
+
+        let position = match self.position(&name) {
+            Some(position) => position,
+            None => return self.fields.push((name, value)),
+        };
+
+        let mut index = 0;
+        self.fields.retain(|(field, _)| {
+            let keep = index <= position || !field.eq_ignore_ascii_case(&name);
+            index += 1;
+            keep
+        });
+        self.fields[position] = (name, value);
+    }
+
+    /// Adds a value for `name`, keeping the values it already had.
+    pub fn append<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.fields.push((
+            strip_line_breaks(name.into()),
+            strip_line_breaks(value.into()),
+        ));
+    }
+
+    /// The first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .map(|position| self.fields[position].1.as_str())
+    }
+
+    /// All values of `name`, in the order they were added.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Removes all values of `name`, returning the first one.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let position = self.position(name)?;
+        let (_, value) = self.fields.remove(position);
+        self.fields
+            .retain(|(field, _)| !field.eq_ignore_ascii_case(name));
+
+        Some(value)
+    }
+
+    /// The number of fields, counting every value of a name.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The fields in the order they were added.
+    pub fn iter(&self) -> HeaderIter<'_> {
+        HeaderIter(self.fields.iter())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|(field, _)| field.eq_ignore_ascii_case(name))
+    }
+}
+
+fn strip_line_breaks(mut field: String) -> String {
+    if field.contains(['\r', '\n', '\0']) {
+        field.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+    }
+
+    field
+}
+
+pub struct HeaderIter<'a>(slice::Iter<'a, (String, String)>);
+
+impl<'a> Iterator for HeaderIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = HeaderIter<'a>;
+
+    fn into_iter(self) -> HeaderIter<'a> {
+        self.iter()
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in iter {
+            headers.append(name, value);
+        }
+
+        headers
+    }
+}
+
+// Serializes the fields the way they are sent, each line ending in CRLF
+impl fmt::Display for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self {
+            write!(f, "{}: {}\r\n", name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_ignores_case() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Length", "13");
+
+        assert_eq!(headers.get("content-length"), Some("13"));
+        assert_eq!(headers.get("CONTENT-LENGTH"), Some("13"));
+        assert!(headers.contains_key("Content-length"));
+        assert_eq!(headers.get("Content-Type"), None);
+    }
+
+    #[test]
+    fn append_keeps_every_value() {
+        let mut headers = HeaderMap::new();
+        headers.append("Set-Cookie", "a=1");
+        headers.append("set-cookie", "b=2");
+
+        assert_eq!(headers.get("Set-Cookie"), Some("a=1"));
+        assert_eq!(
+            headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+            vec!["a=1", "b=2"]
+        );
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[test]
+    fn insert_replaces_all_values_in_place() {
+        let mut headers: HeaderMap = vec![
+            ("Accept", "text/html"),
+            ("Host", "example.com"),
+            ("accept", "text/plain"),
+        ]
+        .into_iter()
+        .collect();
+
+        headers.insert("ACCEPT", "*/*");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![("ACCEPT", "*/*"), ("Host", "example.com")]
+        );
+    }
+
+    #[test]
+    fn line_breaks_are_left_out() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Location", "/a\r\nSet-Cookie: x=1");
+        headers.append("X-\nName", "a\0b");
+
+        assert_eq!(
+            headers.to_string(),
+            "Location: /aSet-Cookie: x=1\r\nX-Name: ab\r\n"
+        );
+    }
+
+    #[test]
+    fn remove_drops_every_value() {
+        let mut headers: HeaderMap = vec![("Via", "a"), ("Host", "example.com"), ("via", "b")]
+            .into_iter()
+            .collect();
+
+        assert_eq!(headers.remove("VIA"), Some("a".to_string()));
+        assert_eq!(headers.get_all("Via").count(), 0);
+        assert_eq!(headers.remove("Via"), None);
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn display_keeps_insertion_order() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Host", "example.com");
+        headers.append("Set-Cookie", "a=1");
+        headers.insert("Content-Type", "text/plain");
+        headers.append("Set-Cookie", "b=2");
+
+        assert_eq!(
+            headers.to_string(),
+            "Host: example.com\r\nSet-Cookie: a=1\r\n\
+            Content-Type: text/plain\r\nSet-Cookie: b=2\r\n"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use super::{find, ChunkedDecoder, Error, ErrorKind, Framing, Request, HEADER_TERMINATOR};
+
+use std::mem;
+
+/// The outcome of feeding bytes to a `RequestParser`.
+#[derive(Debug, PartialEq)]
+pub enum Parsed {
+    /// The request isn't complete yet. All bytes fed so far have been taken.
+    Incomplete,
+    /// The request ended after the given number of bytes of the last input.
+    /// Any bytes after that belong to the next request.
+    Complete(Box<Request>, usize),
+}
+
+enum State {
+    Head,
+    Body {
+        request: Request,
+        length: usize,
+    },
+    Chunked {
+        request: Request,
+        decoder: ChunkedDecoder,
+        header_length: usize,
+    },
+}
+
+/// Parses a request from bytes as they arrive, without needing the whole
+/// request up front.
+///
+/// After a request is complete or an error was returned the parser starts
+/// over with the next request.
+pub struct RequestParser {
+    max_header_size: usize,
+    max_body_size: usize,
+    state: State,
+    // The header while it is incomplete, then the part of the chunked body
+    // the decoder couldn't use yet, at most a line
+    buffer: Vec<u8>,
+}
+
+impl RequestParser {
+    pub fn new(max_header_size: usize, max_body_size: usize) -> RequestParser {
+        RequestParser {
+            max_header_size,
+            max_body_size,
+            state: State::Head,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Whether no part of a request has been fed yet.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.state, State::Head) && self.buffer.is_empty()
+    }
+
+    /// The request once its header has been parsed, while the body is still
+    /// incomplete.
+    pub fn head(&self) -> Option<&Request> {
+        match &self.state {
+            State::Head => None,
+            State::Body { request, .. } | State::Chunked { request, .. } => Some(request),
+        }
+    }
+
+    pub fn feed(&mut self, input: &[u8]) -> Result<Parsed, Error> {
+        let result = self.advance(input);
+
+        if !matches!(result, Ok(Parsed::Incomplete)) {
+            self.state = State::Head;
+            self.buffer.clear();
+        }
+
+        result
+    }
+
+    fn advance(&mut self, input: &[u8]) -> Result<Parsed, Error> {
+        let mut consumed = 0;
+
+        if let State::Head = self.state {
+            consumed = match self.parse_head(input)? {
+                Some(consumed) => consumed,
+                None => return Ok(Parsed::Incomplete),
+            };
+        }
+
+        let input = &input[consumed..];
+
+        match &mut self.state {
+            State::Head => unreachable!(),
+            State::Body { request, length } => {
+                let available = (*length - request.body.len()).min(input.len());
+                request.body.extend_from_slice(&input[..available]);
+                consumed += available;
+
+                if request.body.len() < *length {
+                    return Ok(Parsed::Incomplete);
+                }
+            }
+            State::Chunked {
+                decoder,
+                header_length,
+                ..
+            } => {
+                let buffered = self.buffer.len();
+                self.buffer.extend_from_slice(input);
+
+                let start = decoder.consumed();
+                let complete = decoder
+                    .decode(&self.buffer)
+                    .map_err(|e| e.shift(*header_length))?;
+                let decoded = decoder.consumed() - start;
+                self.buffer.drain(..decoded);
+
+                if decoder.body_length() > self.max_body_size {
+                    return Err(ErrorKind::BodyTooLarge.into());
+                }
+
+                // Trailers are part of the header as far as limits go
+                if *header_length + decoder.trailer_length() > self.max_header_size {
+                    return Err(ErrorKind::HeaderTooLarge.into());
+                }
+
+                if !complete {
+                    return Ok(Parsed::Incomplete);
+                }
+
+                consumed += decoded - buffered;
+            }
+        }
+
+        let request = match mem::replace(&mut self.state, State::Head) {
+            State::Head => unreachable!(),
+            State::Body { request, .. } => request,
+            State::Chunked {
+                mut request,
+                decoder,
+                ..
+            } => {
+                decoder.finish(&mut request);
+                request
+            }
+        };
+
+        Ok(Parsed::Complete(Box::new(request), consumed))
+    }
+
+    // Returns how many bytes of the input belong to the header, once the
+    // header is complete
+    fn parse_head(&mut self, input: &[u8]) -> Result<Option<usize>, Error> {
+        let buffered = self.buffer.len();
+
+        // The terminator may have started in the previous input
+        let search_start = buffered.saturating_sub(HEADER_TERMINATOR.len() - 1);
+        self.buffer.extend_from_slice(input);
+
+        let header_end = match find(&self.buffer[search_start..], HEADER_TERMINATOR) {
+            Some(end) => search_start + end,
+            None if self.buffer.len() >= self.max_header_size => {
+                return Err(ErrorKind::HeaderTooLarge.into())
+            }
+            None => return Ok(None),
+        };
+
+        let header_length = header_end + HEADER_TERMINATOR.len();
+
+        if header_length > self.max_header_size {
+            return Err(ErrorKind::HeaderTooLarge.into());
+        }
+
+        let request = Request::parse_head(&self.buffer[..header_end])?;
+        self.buffer.clear();
+
+        self.state = match request.framing()? {
+            Framing::Length(length) if length > self.max_body_size => {
+                return Err(ErrorKind::BodyTooLarge.into())
+            }
+            Framing::Length(length) => State::Body { request, length },
+            Framing::Chunked => State::Chunked {
+                request,
+                decoder: ChunkedDecoder::new(),
+                header_length,
+            },
+        };
+
+        Ok(Some(header_length - buffered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    fn parser() -> RequestParser {
+        RequestParser::new(8 * 1024, 1024)
+    }
+
+    #[test]
+    fn feed_reports_incomplete_until_header_ends() {
+        let input = b"GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut parser = parser();
+
+        for byte in &input[..input.len() - 1] {
+            assert_eq!(parser.feed(&[*byte]), Ok(Parsed::Incomplete));
+        }
+
+        match parser.feed(&input[input.len() - 1..]).unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.uri.path, "/hello");
+                assert_eq!(request.header_value("Host"), Some("example.com"));
+                assert_eq!(consumed, 1);
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        }
+
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn feed_reads_body_across_inputs() {
+        let mut parser = parser();
+
+        assert_eq!(
+            parser.feed(b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 10\r\n\r\nHello"),
+            Ok(Parsed::Incomplete)
+        );
+
+        match parser.feed(b", world").unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.method, Method::Post);
+                assert_eq!(request.body, b"Hello, wor");
+                assert_eq!(consumed, 5);
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        }
+    }
+
+    #[test]
+    fn head_is_available_while_body_is_incomplete() {
+        let mut parser = parser();
+        assert!(parser.head().is_none());
+
+        parser
+            .feed(b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5")
+            .unwrap();
+        assert!(parser.head().is_none());
+
+        parser.feed(b"\r\n\r\n").unwrap();
+        assert_eq!(parser.head().unwrap().uri.path, "/upload");
+
+        parser.feed(b"Hello").unwrap();
+        assert!(parser.head().is_none());
+    }
+
+    #[test]
+    fn feed_leaves_pipelined_requests() {
+        let input = b"GET /one HTTP/1.1\r\n\
+            Host: example.com\r\n\r\nGET /two HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut parser = parser();
+
+        let consumed = match parser.feed(input).unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.uri.path, "/one");
+                consumed
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        };
+
+        match parser.feed(&input[consumed..]).unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.uri.path, "/two");
+                assert_eq!(consumed, input.len() - 40);
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        }
+    }
+
+    #[test]
+    fn feed_decodes_chunked_body_across_inputs() {
+        let input = b"POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nHello\r\n0\r\n\r\nGET";
+        let mut parser = parser();
+
+        for byte in &input[..input.len() - 4] {
+            assert_eq!(parser.feed(&[*byte]), Ok(Parsed::Incomplete));
+        }
+
+        match parser.feed(&input[input.len() - 4..]).unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.body, b"Hello");
+                assert_eq!(consumed, 1);
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        }
+    }
+
+    #[test]
+    fn feed_keeps_no_chunked_framing() {
+        let mut parser = parser();
+        parser
+            .feed(b"POST / HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n")
+            .unwrap();
+
+        for _ in 0..1000 {
+            assert_eq!(parser.feed(b"1;ext=value\r\na\r\n"), Ok(Parsed::Incomplete));
+        }
+        assert_eq!(parser.feed(b"1;ext"), Ok(Parsed::Incomplete));
+        assert_eq!(parser.buffer, b"1;ext");
+
+        match parser.feed(b"\r\na\r\n0\r\n\r\n").unwrap() {
+            Parsed::Complete(request, consumed) => {
+                assert_eq!(request.body.len(), 1001);
+                assert_eq!(consumed, 10);
+            }
+            Parsed::Incomplete => panic!("request should be complete"),
+        }
+    }
+
+    #[test]
+    fn feed_rejects_large_header_before_it_ends() {
+        let mut parser = RequestParser::new(32, 1024);
+
+        let result = parser.feed(
+            b"GET / HTTP/1.1\r\n\
+            Host: example.com\r\nX-Padding: aaaaaaaaaaaaaaaaaaaa",
+        );
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::HeaderTooLarge);
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn feed_rejects_large_body() {
+        let mut parser = RequestParser::new(1024, 4);
+
+        let result = parser.feed(
+            b"POST / HTTP/1.1\r\n\
+            Host: example.com\r\nContent-Length: 5\r\n\r\n",
+        );
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::BodyTooLarge);
+    }
+}
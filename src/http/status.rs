@@ -0,0 +1,211 @@
+use std::fmt;
+
+// Defines `Status` along with the code and reason phrase of each variant,
+// so the three can't get out of step
+macro_rules! statuses {
+    ($($(#[$doc:meta])* $variant:ident = $code:literal, $reason:literal;)+) => {
+        /// Response status codes as listed in the IANA HTTP Status Code
+        /// Registry.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Status {
+            $($(#[$doc])* $variant,)+
+            /// A code from 100 to 599 that isn't registered. Only
+            /// `Status::from_u16` creates them, so they are always valid and
+            /// never stand in for a named variant.
+            Custom(UnregisteredCode),
+        }
+
+        impl Status {
+            /// The status for `code`, as long as it is in the range of 100
+            /// to 599 HTTP defines classes for.
+            pub fn from_u16(code: u16) -> Option<Status> {
+                match code {
+                    $($code => Some(Status::$variant),)+
+                    100..=599 => Some(Status::Custom(UnregisteredCode(code))),
+                    _ => None,
+                }
+            }
+
+            pub fn as_u16(&self) -> u16 {
+                match self {
+                    $(Status::$variant => $code,)+
+                    Status::Custom(code) => code.0,
+                }
+            }
+
+            /// The reason phrase the registry lists, unless the code isn't
+            /// registered.
+            pub fn canonical_reason(&self) -> Option<&'static str> {
+                match self {
+                    $(Status::$variant => Some($reason),)+
+                    Status::Custom(_) => None,
+                }
+            }
+        }
+    };
+}
+
+/// The code of a `Status::Custom`, see `Status::from_u16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnregisteredCode(u16);
+
+statuses! {
+    Continue = 100, "Continue";
+    SwitchingProtocols = 101, "Switching Protocols";
+    Processing = 102, "Processing";
+    EarlyHints = 103, "Early Hints";
+
+    Ok = 200, "OK";
+    Created = 201, "Created";
+    Accepted = 202, "Accepted";
+    NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+    NoContent = 204, "No Content";
+    ResetContent = 205, "Reset Content";
+    PartialContent = 206, "Partial Content";
+    MultiStatus = 207, "Multi-Status";
+    AlreadyReported = 208, "Already Reported";
+    ImUsed = 226, "IM Used";
+
+    MultipleChoices = 300, "Multiple Choices";
+    MovedPermanently = 301, "Moved Permanently";
+    Found = 302, "Found";
+    SeeOther = 303, "See Other";
+    NotModified = 304, "Not Modified";
+    UseProxy = 305, "Use Proxy";
+    TemporaryRedirect = 307, "Temporary Redirect";
+    PermanentRedirect = 308, "Permanent Redirect";
+
+    BadRequest = 400, "Bad Request";
+    Unauthorized = 401, "Unauthorized";
+    PaymentRequired = 402, "Payment Required";
+    Forbidden = 403, "Forbidden";
+    NotFound = 404, "Not Found";
+    MethodNotAllowed = 405, "Method Not Allowed";
+    NotAcceptable = 406, "Not Acceptable";
+    ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+    RequestTimeout = 408, "Request Timeout";
+    Conflict = 409, "Conflict";
+    Gone = 410, "Gone";
+    LengthRequired = 411, "Length Required";
+    PreconditionFailed = 412, "Precondition Failed";
+    ContentTooLarge = 413, "Content Too Large";
+    UriTooLong = 414, "URI Too Long";
+    UnsupportedMediaType = 415, "Unsupported Media Type";
+    RangeNotSatisfiable = 416, "Range Not Satisfiable";
+    ExpectationFailed = 417, "Expectation Failed";
+    MisdirectedRequest = 421, "Misdirected Request";
+    UnprocessableContent = 422, "Unprocessable Content";
+    Locked = 423, "Locked";
+    FailedDependency = 424, "Failed Dependency";
+    TooEarly = 425, "Too Early";
+    UpgradeRequired = 426, "Upgrade Required";
+    PreconditionRequired = 428, "Precondition Required";
+    TooManyRequests = 429, "Too Many Requests";
+    RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+    UnavailableForLegalReasons = 451, "Unavailable For Legal Reasons";
+
+    InternalServerError = 500, "Internal Server Error";
+    NotImplemented = 501, "Not Implemented";
+    BadGateway = 502, "Bad Gateway";
+    ServiceUnavailable = 503, "Service Unavailable";
+    GatewayTimeout = 504, "Gateway Timeout";
+    VersionNotSupported = 505, "HTTP Version Not Supported";
+    VariantAlsoNegotiates = 506, "Variant Also Negotiates";
+    InsufficientStorage = 507, "Insufficient Storage";
+    LoopDetected = 508, "Loop Detected";
+    NotExtended = 510, "Not Extended";
+    NetworkAuthenticationRequired = 511, "Network Authentication Required";
+}
+
+impl Status {
+    /// Whether a response with this status may have a body. Informational
+    /// responses, `204 No Content` and `304 Not Modified` never do.
+    pub fn allows_body(&self) -> bool {
+        let code = self.as_u16();
+        code >= 200 && code != 204 && code != 304
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.canonical_reason() {
+            Some(reason) => write!(f, "{} {}", self.as_u16(), reason),
+            None => write!(f, "{}", self.as_u16()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseClass {
+    Informational,
+    Successful,
+    Redirection,
+    ClientError,
+    ServerError,
+}
+
+impl ResponseClass {
+    /// The class is the first digit of the code, which is always one of the
+    /// classes HTTP defines since codes are limited to 100 through 599.
+    pub fn new(status: &Status) -> ResponseClass {
+        match status.as_u16() {
+            100..=199 => Self::Informational,
+            200..=299 => Self::Successful,
+            300..=399 => Self::Redirection,
+            400..=499 => Self::ClientError,
+            _ => Self::ServerError,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_codes() {
+        assert_eq!(Status::from_u16(403), Some(Status::Forbidden));
+        assert_eq!(Status::Forbidden.as_u16(), 403);
+        assert_eq!(
+            Status::from_u16(418),
+            Some(Status::Custom(UnregisteredCode(418)))
+        );
+        assert_eq!(Status::from_u16(418).unwrap().as_u16(), 418);
+        assert_eq!(Status::from_u16(99), None);
+        assert_eq!(Status::from_u16(600), None);
+
+        for code in 100..=599 {
+            assert_eq!(Status::from_u16(code).unwrap().as_u16(), code);
+        }
+    }
+
+    #[test]
+    fn status_display() {
+        assert_eq!(Status::Forbidden.to_string(), "403 Forbidden");
+        assert_eq!(
+            Status::VersionNotSupported.to_string(),
+            "505 HTTP Version Not Supported"
+        );
+        assert_eq!(Status::from_u16(599).unwrap().to_string(), "599");
+    }
+
+    #[test]
+    fn status_allows_body() {
+        assert!(Status::Ok.allows_body());
+        assert!(Status::NotFound.allows_body());
+        assert!(!Status::Continue.allows_body());
+        assert!(!Status::NoContent.allows_body());
+        assert!(!Status::NotModified.allows_body());
+    }
+
+    #[test]
+    fn response_class() {
+        let class = |code| ResponseClass::new(&Status::from_u16(code).unwrap());
+
+        assert_eq!(class(101), ResponseClass::Informational);
+        assert_eq!(class(204), ResponseClass::Successful);
+        assert_eq!(class(308), ResponseClass::Redirection);
+        assert_eq!(class(451), ResponseClass::ClientError);
+        assert_eq!(class(599), ResponseClass::ServerError);
+    }
+}
@@ -0,0 +1,1047 @@
+use super::{Error, ErrorKind, HeaderMap};
+use crate::parser_combinator::{is_tchar, is_vchar};
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Typed access to common headers, on top of the raw `HeaderMap`.
+///
+/// Getters return `Ok(None)` if the header is missing and an error if it
+/// can't be parsed. Setters replace any value the header had.
+pub trait TypedHeaders {
+    fn header_map(&self) -> &HeaderMap;
+
+    fn header_map_mut(&mut self) -> &mut HeaderMap;
+
+    fn content_length(&self) -> Result<Option<u64>, Error> {
+        parse_single(self.header_map(), "Content-Length", |value| {
+            match !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+                true => value.parse().or(Err(invalid())),
+                false => Err(invalid()),
+            }
+        })
+    }
+
+    fn set_content_length(&mut self, length: u64) {
+        self.header_map_mut()
+            .insert("Content-Length", length.to_string());
+    }
+
+    fn content_type(&self) -> Result<Option<mime::Mime>, Error> {
+        parse_single(self.header_map(), "Content-Type", |value| {
+            value.parse().or(Err(invalid()))
+        })
+    }
+
+    fn set_content_type(&mut self, mime: &mime::Mime) {
+        self.header_map_mut()
+            .insert("Content-Type", mime.to_string());
+    }
+
+    fn host(&self) -> Result<Option<Host>, Error> {
+        parse_single(self.header_map(), "Host", str::parse)
+    }
+
+    fn set_host(&mut self, host: &Host) {
+        self.header_map_mut().insert("Host", host.to_string());
+    }
+
+    fn accept(&self) -> Result<Option<Vec<Quality<mime::Mime>>>, Error> {
+        parse_list(self.header_map(), "Accept", |element| {
+            Quality::parse(element, |mime| mime.parse().or(Err(invalid())))
+        })
+    }
+
+    fn set_accept(&mut self, accept: &[Quality<mime::Mime>]) {
+        self.header_map_mut().insert("Accept", join(accept));
+    }
+
+    fn accept_encoding(&self) -> Result<Option<Vec<Quality<String>>>, Error> {
+        parse_list(self.header_map(), "Accept-Encoding", |element| {
+            Quality::parse(element, |coding| match is_token(coding) {
+                true => Ok(coding.to_string()),
+                false => Err(invalid()),
+            })
+        })
+    }
+
+    fn set_accept_encoding(&mut self, accept_encoding: &[Quality<String>]) {
+        self.header_map_mut()
+            .insert("Accept-Encoding", join(accept_encoding));
+    }
+
+    fn authorization(&self) -> Result<Option<Authorization>, Error> {
+        parse_single(self.header_map(), "Authorization", str::parse)
+    }
+
+    fn set_authorization(&mut self, authorization: &Authorization) {
+        self.header_map_mut()
+            .insert("Authorization", authorization.to_string());
+    }
+
+    fn cache_control(&self) -> Result<Option<CacheControl>, Error> {
+        parse_list(self.header_map(), "Cache-Control", Directive::parse)
+            .map(|directives| directives.map(|directives| CacheControl { directives }))
+    }
+
+    fn set_cache_control(&mut self, cache_control: &CacheControl) {
+        self.header_map_mut()
+            .insert("Cache-Control", cache_control.to_string());
+    }
+
+    fn if_none_match(&self) -> Result<Option<IfNoneMatch>, Error> {
+        match self.header_map().get("If-None-Match") {
+            Some(value) if value.trim() == "*" => Ok(Some(IfNoneMatch::Any)),
+            _ => parse_list(self.header_map(), "If-None-Match", str::parse)
+                .map(|tags| tags.map(IfNoneMatch::Tags)),
+        }
+    }
+
+    fn set_if_none_match(&mut self, if_none_match: &IfNoneMatch) {
+        self.header_map_mut()
+            .insert("If-None-Match", if_none_match.to_string());
+    }
+
+    fn if_modified_since(&self) -> Result<Option<SystemTime>, Error> {
+        parse_single(self.header_map(), "If-Modified-Since", parse_date)
+    }
+
+    fn set_if_modified_since(&mut self, time: SystemTime) {
+        self.header_map_mut()
+            .insert("If-Modified-Since", HttpDate(time).to_string());
+    }
+
+    fn range(&self) -> Result<Option<Range>, Error> {
+        parse_single(self.header_map(), "Range", str::parse)
+    }
+
+    fn set_range(&mut self, range: &Range) {
+        self.header_map_mut().insert("Range", range.to_string());
+    }
+
+    fn date(&self) -> Result<Option<SystemTime>, Error> {
+        parse_single(self.header_map(), "Date", parse_date)
+    }
+
+    fn set_date(&mut self, time: SystemTime) {
+        self.header_map_mut()
+            .insert("Date", HttpDate(time).to_string());
+    }
+
+    /// The URI reference as it was sent, since it may be relative.
+    fn location(&self) -> Result<Option<&str>, Error> {
+        parse_single(self.header_map(), "Location", |value| {
+            match !value.is_empty() && value.bytes().all(is_vchar) {
+                true => Ok(value),
+                false => Err(invalid()),
+            }
+        })
+    }
+
+    fn set_location(&mut self, location: &str) {
+        self.header_map_mut().insert("Location", location);
+    }
+}
+
+impl TypedHeaders for HeaderMap {
+    fn header_map(&self) -> &HeaderMap {
+        self
+    }
+
+    fn header_map_mut(&mut self) -> &mut HeaderMap {
+        self
+    }
+}
+
+fn invalid() -> Error {
+    ErrorKind::InvalidHeaderValue.into()
+}
+
+fn is_token(input: &str) -> bool {
+    !input.is_empty() && input.bytes().all(is_tchar)
+}
+
+fn parse_single<'a, T, F>(headers: &'a HeaderMap, name: &str, parse: F) -> Result<Option<T>, Error>
+where
+    F: FnOnce(&'a str) -> Result<T, Error>,
+{
+    headers
+        .get(name)
+        .map(|value| parse(value.trim()))
+        .transpose()
+}
+
+// Lists may be split over several fields and may contain empty elements
+fn parse_list<T, F>(headers: &HeaderMap, name: &str, parse: F) -> Result<Option<Vec<T>>, Error>
+where
+    F: Fn(&str) -> Result<T, Error>,
+{
+    if !headers.contains_key(name) {
+        return Ok(None);
+    }
+
+    headers
+        .get_all(name)
+        .flat_map(split_list)
+        .map(parse)
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+// Splits at commas that aren't part of a quoted string
+fn split_list(value: &str) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (index, character) in value.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                elements.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(&value[start..]);
+
+    elements
+        .into_iter()
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+        .collect()
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The `Host` header: a host name or IP address and an optional port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    pub name: String,
+    pub port: Option<u16>,
+}
+
+impl FromStr for Host {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Host, Error> {
+        // IPv6 addresses are in brackets, since they contain colons
+        let (name, port) = match input.rfind(':') {
+            Some(colon) if !input[colon..].contains(']') => {
+                let port = input[colon + 1..].parse().or(Err(invalid()))?;
+                (&input[..colon], Some(port))
+            }
+            _ => (input, None),
+        };
+
+        let valid = |b: u8| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%[]:".contains(&b);
+
+        if name.is_empty() || !name.bytes().all(valid) {
+            return Err(invalid());
+        }
+
+        Ok(Host {
+            name: name.to_string(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.name, port),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// An element of a list like `Accept`, with its weight in thousandths.
+/// Elements without a `q` parameter have the weight 1000.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quality<T> {
+    pub item: T,
+    pub quality: u16,
+}
+
+impl<T> Quality<T> {
+    pub fn new(item: T, quality: u16) -> Quality<T> {
+        Quality { item, quality }
+    }
+
+    // The weight is the first parameter named `q`, anything before it
+    // belongs to the item
+    fn parse<F>(element: &str, parse_item: F) -> Result<Quality<T>, Error>
+    where
+        F: FnOnce(&str) -> Result<T, Error>,
+    {
+        let mut quality = 1000;
+        let mut item = element;
+
+        for (index, _) in element.match_indices(';') {
+            let parameter = element[index + 1..].trim_start();
+
+            // Compared as bytes, the parameter may start with a multibyte
+            // character
+            let is_weight = parameter
+                .as_bytes()
+                .get(..2)
+                .is_some_and(|name| name.eq_ignore_ascii_case(b"q="));
+
+            if is_weight {
+                quality = parse_weight(&parameter[2..])?;
+                item = element[..index].trim_end();
+                break;
+            }
+        }
+
+        Ok(Quality {
+            item: parse_item(item)?,
+            quality,
+        })
+    }
+}
+
+// "0", "0.5", "1.000" and so on, at most three decimals
+fn parse_weight(input: &str) -> Result<u16, Error> {
+    let (integer, fraction) = match input.find('.') {
+        Some(dot) => (&input[..dot], &input[dot + 1..]),
+        None => (input, ""),
+    };
+
+    if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let fraction = format!("{:0<3}", fraction)
+        .parse::<u16>()
+        .or(Err(invalid()))?;
+
+    match integer {
+        "0" => Ok(fraction),
+        "1" if fraction == 0 => Ok(1000),
+        _ => Err(invalid()),
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Quality<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quality {
+            1000 => write!(f, "{}", self.item),
+            quality => {
+                let weight = format!("{:03}", quality);
+                write!(f, "{};q=0.{}", self.item, weight.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+/// The `Authorization` header. The credentials are kept as they were sent,
+/// their format depends on the scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    pub scheme: String,
+    pub credentials: String,
+}
+
+impl FromStr for Authorization {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Authorization, Error> {
+        let (scheme, credentials) = match input.find(' ') {
+            Some(space) => (&input[..space], input[space + 1..].trim_start()),
+            None => (input, ""),
+        };
+
+        if !is_token(scheme) {
+            return Err(invalid());
+        }
+
+        Ok(Authorization {
+            scheme: scheme.to_string(),
+            credentials: credentials.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.credentials.is_empty() {
+            true => write!(f, "{}", self.scheme),
+            false => write!(f, "{} {}", self.scheme, self.credentials),
+        }
+    }
+}
+
+/// The directives of a `Cache-Control` header, in the order they were sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    name: String,
+    value: Option<String>,
+}
+
+impl Directive {
+    fn parse(input: &str) -> Result<Directive, Error> {
+        let (name, value) = match input.find('=') {
+            Some(equals) => (&input[..equals], Some(unquote(&input[equals + 1..])?)),
+            None => (input, None),
+        };
+
+        if !is_token(name) {
+            return Err(invalid());
+        }
+
+        Ok(Directive {
+            name: name.to_ascii_lowercase(),
+            value,
+        })
+    }
+}
+
+impl CacheControl {
+    pub fn new() -> CacheControl {
+        CacheControl::default()
+    }
+
+    /// Adds a directive like `no-store` or, with a value, `max-age=60`.
+    pub fn directive(mut self, name: &str, value: Option<&str>) -> CacheControl {
+        self.directives.push(Directive {
+            name: name.to_ascii_lowercase(),
+            value: value.map(str::to_string),
+        });
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    /// The value of a directive, if it is there and has one.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.find(name)
+            .and_then(|directive| directive.value.as_deref())
+    }
+
+    pub fn max_age(&self) -> Option<u64> {
+        self.value("max-age").and_then(|age| age.parse().ok())
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+
+    fn find(&self, name: &str) -> Option<&Directive> {
+        self.directives
+            .iter()
+            .find(|directive| directive.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, directive) in self.directives.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+
+            match &directive.value {
+                Some(value) if is_token(value) => write!(f, "{}={}", directive.name, value)?,
+                Some(value) => write!(f, "{}={}", directive.name, quote(value))?,
+                None => write!(f, "{}", directive.name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Takes a token or a quoted string
+fn unquote(input: &str) -> Result<String, Error> {
+    if is_token(input) {
+        return Ok(input.to_string());
+    }
+
+    let inner = input
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    let mut value = String::new();
+    let mut escaped = false;
+
+    for character in inner.chars() {
+        match character {
+            _ if escaped => {
+                value.push(character);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => return Err(invalid()),
+            _ => value.push(character),
+        }
+    }
+
+    match escaped {
+        true => Err(invalid()),
+        false => Ok(value),
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// An entity tag like `"xyzzy"` or, if it is weak, `W/"xyzzy"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    pub tag: String,
+}
+
+impl EntityTag {
+    pub fn strong(tag: &str) -> EntityTag {
+        EntityTag {
+            weak: false,
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn weak(tag: &str) -> EntityTag {
+        EntityTag {
+            weak: true,
+            tag: tag.to_string(),
+        }
+    }
+
+    /// The weak comparison `If-None-Match` uses, which ignores whether the
+    /// tags are weak.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl FromStr for EntityTag {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<EntityTag, Error> {
+        let (weak, rest) = match input.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+
+        let tag = rest
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .ok_or_else(invalid)?;
+
+        if !tag.bytes().all(|b| b != b'"' && (is_vchar(b) || b >= 0x80)) {
+            return Err(invalid());
+        }
+
+        Ok(EntityTag {
+            weak,
+            tag: tag.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.weak {
+            true => write!(f, "W/\"{}\"", self.tag),
+            false => write!(f, "\"{}\"", self.tag),
+        }
+    }
+}
+
+/// The `If-None-Match` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    /// `*`, which matches any current representation.
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl IfNoneMatch {
+    /// Whether a representation with `tag` matches, meaning the client
+    /// already has it.
+    pub fn matches(&self, tag: &EntityTag) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|other| other.weak_eq(tag)),
+        }
+    }
+}
+
+impl fmt::Display for IfNoneMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IfNoneMatch::Any => write!(f, "*"),
+            IfNoneMatch::Tags(tags) => write!(f, "{}", join(tags)),
+        }
+    }
+}
+
+/// The `Range` header. Only byte ranges are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub ranges: Vec<ByteRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// From the first to the last byte, both included.
+    FromTo(u64, u64),
+    /// From a byte to the end.
+    From(u64),
+    /// The given number of bytes at the end.
+    Last(u64),
+}
+
+impl FromStr for Range {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Range, Error> {
+        let unit_end = input.find('=').ok_or_else(invalid)?;
+
+        if !input[..unit_end].eq_ignore_ascii_case("bytes") {
+            return Err(invalid());
+        }
+
+        let ranges = split_list(&input[unit_end + 1..])
+            .into_iter()
+            .map(parse_byte_range)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match ranges.is_empty() {
+            true => Err(invalid()),
+            false => Ok(Range { ranges }),
+        }
+    }
+}
+
+fn parse_byte_range(input: &str) -> Result<ByteRange, Error> {
+    let position =
+        |input: &str| match !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+            true => input.parse::<u64>().or(Err(invalid())),
+            false => Err(invalid()),
+        };
+
+    let dash = input.find('-').ok_or_else(invalid)?;
+    let (first, last) = (&input[..dash], &input[dash + 1..]);
+
+    match (first.is_empty(), last.is_empty()) {
+        (true, _) => position(last).map(ByteRange::Last),
+        (false, true) => position(first).map(ByteRange::From),
+        (false, false) => {
+            let (first, last) = (position(first)?, position(last)?);
+
+            match first <= last {
+                true => Ok(ByteRange::FromTo(first, last)),
+                false => Err(invalid()),
+            }
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes=")?;
+
+        for (index, range) in self.ranges.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+
+            match range {
+                ByteRange::FromTo(first, last) => write!(f, "{}-{}", first, last)?,
+                ByteRange::From(first) => write!(f, "{}-", first)?,
+                ByteRange::Last(length) => write!(f, "-{}", length)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats a point in time the way HTTP headers carry dates, for example
+/// `Sun, 06 Nov 1994 08:49:37 GMT`. Times before 1970 are sent as 1970.
+pub struct HttpDate(pub SystemTime);
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let days = (seconds / 86400) as i64;
+        let (year, month, day) = civil_from_days(days);
+        let time = seconds % 86400;
+
+        write!(
+            f,
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[(days + 4).rem_euclid(7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )
+    }
+}
+
+/// Parses dates in any of the three formats HTTP allows: the preferred
+/// `Sun, 06 Nov 1994 08:49:37 GMT`, the obsolete `Sunday, 06-Nov-94
+/// 08:49:37 GMT` and asctime's `Sun Nov  6 08:49:37 1994`.
+fn parse_date(input: &str) -> Result<SystemTime, Error> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+
+    let (day, month, year, time) = match parts.as_slice() {
+        [weekday, day, month, year, time, "GMT"] if weekday.ends_with(',') => {
+            (*day, *month, parse_number(year)?, *time)
+        }
+        [weekday, date, time, "GMT"] if weekday.ends_with(',') => {
+            let mut date = date.split('-');
+            let (day, month, year) = match (date.next(), date.next(), date.next()) {
+                (Some(day), Some(month), Some(year)) if year.len() == 2 => (day, month, year),
+                _ => return Err(invalid()),
+            };
+
+            // Two digit years that would be more than 50 years ahead are
+            // in the past
+            let year = match parse_number(year)? {
+                year if year < 70 => 2000 + year,
+                year => 1900 + year,
+            };
+
+            (day, month, year, *time)
+        }
+        [_, month, day, time, year] => (*day, *month, parse_number(year)?, *time),
+        _ => return Err(invalid()),
+    };
+
+    let month = MONTHS
+        .iter()
+        .position(|&name| name == month)
+        .ok_or_else(invalid)? as i64
+        + 1;
+    let day = parse_number(day)?;
+    let days = days_from_civil(year, month, day);
+
+    // Catches days past the end of the month
+    if year < 1970 || civil_from_days(days) != (year, month, day) {
+        return Err(invalid());
+    }
+
+    let mut time = time.split(':').map(parse_number);
+    let (hours, minutes, seconds) = match (time.next(), time.next(), time.next(), time.next()) {
+        (Some(hours), Some(minutes), Some(seconds), None) => (hours?, minutes?, seconds?),
+        _ => return Err(invalid()),
+    };
+
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return Err(invalid());
+    }
+
+    let seconds = days * 86400 + hours * 3600 + minutes * 60 + seconds;
+
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+}
+
+fn parse_number(input: &str) -> Result<i64, Error> {
+    match !input.is_empty() && input.len() <= 4 && input.bytes().all(|b| b.is_ascii_digit()) {
+        true => input.parse().or(Err(invalid())),
+        false => Err(invalid()),
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar, see
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(fields: &[(&str, &str)]) -> HeaderMap {
+        fields.iter().cloned().collect()
+    }
+
+    #[test]
+    fn content_length() {
+        assert_eq!(headers(&[]).content_length(), Ok(None));
+        assert_eq!(
+            headers(&[("Content-Length", "42")]).content_length(),
+            Ok(Some(42))
+        );
+        assert!(headers(&[("Content-Length", "-1")])
+            .content_length()
+            .is_err());
+
+        let mut headers = HeaderMap::new();
+        headers.set_content_length(7);
+        assert_eq!(headers.get("Content-Length"), Some("7"));
+    }
+
+    #[test]
+    fn content_type() {
+        let html = headers(&[("Content-Type", "text/html; charset=utf-8")]);
+        assert_eq!(html.content_type(), Ok(Some(mime::TEXT_HTML_UTF_8)));
+
+        assert!(headers(&[("Content-Type", "text")]).content_type().is_err());
+    }
+
+    #[test]
+    fn host() {
+        let host = |value| headers(&[("Host", value)]).host();
+
+        assert_eq!(
+            host("example.com:8080"),
+            Ok(Some(Host {
+                name: "example.com".to_string(),
+                port: Some(8080)
+            }))
+        );
+        assert_eq!(
+            host("[::1]"),
+            Ok(Some(Host {
+                name: "[::1]".to_string(),
+                port: None
+            }))
+        );
+        assert_eq!(host("[::1]:80").unwrap().unwrap().port, Some(80));
+        assert!(host("example.com:http").is_err());
+        assert!(host("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn accept() {
+        let headers = headers(&[
+            ("Accept", "text/html, application/json;q=0.5"),
+            ("Accept", "*/*; q=0.125"),
+        ]);
+
+        let accept = headers.accept().unwrap().unwrap();
+
+        assert_eq!(
+            accept,
+            vec![
+                Quality::new(mime::TEXT_HTML, 1000),
+                Quality::new(mime::APPLICATION_JSON, 500),
+                Quality::new(mime::STAR_STAR, 125),
+            ]
+        );
+        assert_eq!(
+            join(&accept),
+            "text/html, application/json;q=0.5, */*;q=0.125"
+        );
+    }
+
+    #[test]
+    fn accept_encoding() {
+        let mut headers = headers(&[("Accept-Encoding", "gzip, identity;q=0, ,br;q=1.0")]);
+
+        assert_eq!(
+            headers.accept_encoding(),
+            Ok(Some(vec![
+                Quality::new("gzip".to_string(), 1000),
+                Quality::new("identity".to_string(), 0),
+                Quality::new("br".to_string(), 1000),
+            ]))
+        );
+
+        headers.insert("Accept-Encoding", "gzip;q=1.5");
+        assert!(headers.accept_encoding().is_err());
+        headers.insert("Accept-Encoding", "gzip;q=0.1234");
+        assert!(headers.accept_encoding().is_err());
+        headers.insert("Accept-Encoding", "gzip;é");
+        assert!(headers.accept_encoding().is_err());
+    }
+
+    #[test]
+    fn accept_with_multibyte_parameter() {
+        let headers = headers(&[("Accept", "text/html;aé, text/plain;é;q=0.5")]);
+
+        let accept = headers.accept();
+
+        assert_eq!(accept, Err(invalid()));
+    }
+
+    #[test]
+    fn authorization() {
+        let mut headers = headers(&[("Authorization", "Basic dXNlcjpwYXNz")]);
+
+        assert_eq!(
+            headers.authorization(),
+            Ok(Some(Authorization {
+                scheme: "Basic".to_string(),
+                credentials: "dXNlcjpwYXNz".to_string()
+            }))
+        );
+
+        headers.insert("Authorization", "B@sic abc");
+        assert!(headers.authorization().is_err());
+    }
+
+    #[test]
+    fn cache_control() {
+        let headers = headers(&[(
+            "Cache-Control",
+            "no-cache=\"Set-Cookie, X-Id\", Max-Age=60, no-store",
+        )]);
+
+        let cache_control = headers.cache_control().unwrap().unwrap();
+
+        assert_eq!(cache_control.value("no-cache"), Some("Set-Cookie, X-Id"));
+        assert_eq!(cache_control.max_age(), Some(60));
+        assert!(cache_control.no_store());
+        assert_eq!(
+            cache_control.to_string(),
+            "no-cache=\"Set-Cookie, X-Id\", max-age=60, no-store"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.set_cache_control(&CacheControl::new().directive("private", None));
+        assert_eq!(headers.get("Cache-Control"), Some("private"));
+        headers.insert("Cache-Control", "max-age=\"60");
+        assert!(headers.cache_control().is_err());
+    }
+
+    #[test]
+    fn if_none_match() {
+        let if_none_match = |value| headers(&[("If-None-Match", value)]).if_none_match();
+
+        assert_eq!(if_none_match("*"), Ok(Some(IfNoneMatch::Any)));
+
+        let tags = if_none_match("\"a,b\", W/\"c\"").unwrap().unwrap();
+        assert_eq!(
+            tags,
+            IfNoneMatch::Tags(vec![EntityTag::strong("a,b"), EntityTag::weak("c")])
+        );
+        assert!(tags.matches(&EntityTag::strong("c")));
+        assert!(!tags.matches(&EntityTag::strong("a")));
+        assert_eq!(tags.to_string(), "\"a,b\", W/\"c\"");
+
+        assert!(if_none_match("abc").is_err());
+    }
+
+    #[test]
+    fn range() {
+        let range = |value| headers(&[("Range", value)]).range();
+
+        assert_eq!(
+            range("bytes=0-499, 1000-, -500"),
+            Ok(Some(Range {
+                ranges: vec![
+                    ByteRange::FromTo(0, 499),
+                    ByteRange::From(1000),
+                    ByteRange::Last(500)
+                ]
+            }))
+        );
+        assert_eq!(
+            range("bytes=0-499,-500").unwrap().unwrap().to_string(),
+            "bytes=0-499, -500"
+        );
+        assert!(range("bytes=500-499").is_err());
+        assert!(range("bytes=").is_err());
+        assert!(range("items=0-1").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        for date in &[
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            assert_eq!(headers(&[("Date", date)]).date(), Ok(Some(expected)));
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.set_if_modified_since(expected);
+        assert_eq!(
+            headers.get("If-Modified-Since"),
+            Some("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+        assert_eq!(headers.if_modified_since(), Ok(Some(expected)));
+
+        headers.insert("Date", "Tue, 30 Feb 2021 00:00:00 GMT");
+        assert!(headers.date().is_err());
+        headers.insert("Date", "Sun, 06 Nov 1994 24:00:00 GMT");
+        assert!(headers.date().is_err());
+    }
+
+    #[test]
+    fn date_formatting() {
+        let date = |seconds| HttpDate(UNIX_EPOCH + Duration::from_secs(seconds)).to_string();
+
+        assert_eq!(date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(date(951782400), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(date(4102444799), "Thu, 31 Dec 2099 23:59:59 GMT");
+    }
+
+    #[test]
+    fn location() {
+        let mut headers = headers(&[("Location", "/new/place?x=1")]);
+        assert_eq!(headers.location(), Ok(Some("/new/place?x=1")));
+
+        headers.set_location("https://example.com/");
+        assert_eq!(headers.location(), Ok(Some("https://example.com/")));
+    }
+}
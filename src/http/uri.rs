@@ -0,0 +1,417 @@
+use super::{Error, ErrorKind, Host, Method};
+
+use std::{borrow::Cow, fmt, slice};
+
+/// The form the request-target was sent in. The path and query of the
+/// origin and absolute forms are in `Request::uri`, the other forms leave
+/// its path empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestTarget {
+    /// `/where?q=now`, what requests to origin servers use.
+    Origin,
+    /// `http://www.example.org/where?q=now`, what requests to proxies use.
+    Absolute { scheme: String, authority: Host },
+    /// `www.example.com:80`, only used by `CONNECT`.
+    Authority(Host),
+    /// `*`, only used by `OPTIONS` for the server as a whole.
+    Asterisk,
+}
+
+impl RequestTarget {
+    /// Tells the form apart and checks that `method` may use it.
+    pub fn parse(input: &str, method: &Method) -> Result<(RequestTarget, Uri), Error> {
+        let invalid = || Error::from(ErrorKind::InvalidRequestTarget);
+
+        match (input, method) {
+            ("*", Method::Options) => Ok((RequestTarget::Asterisk, Uri::default())),
+            (_, Method::Connect) => match input.parse::<Host>() {
+                Ok(authority) if authority.port.is_some() => {
+                    Ok((RequestTarget::Authority(authority), Uri::default()))
+                }
+                _ => Err(invalid()),
+            },
+            _ if input.starts_with('/') => Ok((RequestTarget::Origin, Uri::parse(input)?)),
+            _ => {
+                let scheme_end = input.find("://").ok_or_else(invalid)?;
+                let scheme = &input[..scheme_end];
+                let rest = &input[scheme_end + 3..];
+
+                let valid_scheme = scheme
+                    .bytes()
+                    .next()
+                    .is_some_and(|b| b.is_ascii_alphabetic())
+                    && scheme
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b));
+
+                if !valid_scheme {
+                    return Err(invalid());
+                }
+
+                let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+                let authority = rest[..authority_end].parse().map_err(|_| invalid())?;
+
+                // An empty path is the same as "/"
+                let uri = match &rest[authority_end..] {
+                    path if path.starts_with('/') => Uri::parse(path)?,
+                    rest => Uri::parse(&format!("/{}", rest))?,
+                };
+
+                let target = RequestTarget::Absolute {
+                    scheme: scheme.to_ascii_lowercase(),
+                    authority,
+                };
+
+                Ok((target, uri))
+            }
+        }
+    }
+}
+
+/// A request target split into its parts.
+///
+/// The path is percent-decoded and normalized: `.` and `..` segments are
+/// resolved and repeated slashes are merged. The query is kept as it was
+/// sent.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Uri {
+    pub path: String,
+    pub query: Option<String>,
+}
+
+impl Uri {
+    /// A URI without a query. `path` is normalized like parsed ones.
+    ///
+    /// # Panics
+    ///
+    /// If `path` isn't a valid absolute path.
+    pub fn new(path: &str) -> Uri {
+        let normalized = match path.starts_with('/') {
+            true => normalize_path(path).ok(),
+            false => None,
+        };
+
+        Uri {
+            path: normalized.unwrap_or_else(|| panic!("Invalid path: {}", path)),
+            query: None,
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        // Fragments are only meaningful to the client and never sent
+        if input.contains('#') {
+            return Err(ErrorKind::InvalidRequestTarget.into());
+        }
+
+        let (path, query) = match input.find('?') {
+            Some(question) => (&input[..question], Some(input[question + 1..].to_string())),
+            None => (input, None),
+        };
+
+        if !path.starts_with('/') {
+            return Err(ErrorKind::InvalidRequestTarget.into());
+        }
+
+        Ok(Uri {
+            path: normalize_path(path)?,
+            query,
+        })
+    }
+
+    /// The segments of the path, without the slashes between them.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.path.split('/').filter(|segment| !segment.is_empty())
+    }
+
+    /// The parameters of the query, decoded the way HTML forms encode them.
+    pub fn query_params(&self) -> QueryParams {
+        let query = self.query.as_deref().unwrap_or_default();
+
+        QueryParams {
+            params: query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.find('=') {
+                    Some(equals) => (
+                        decode_form(&pair[..equals]),
+                        decode_form(&pair[equals + 1..]),
+                    ),
+                    None => (decode_form(pair), String::new()),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Encodes the path again, so the result is a valid request-target
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.path.bytes() {
+            match byte {
+                b'/' | b':' | b'@' | b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\''
+                | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' => write!(f, "{}", byte as char)?,
+                _ if byte.is_ascii_alphanumeric() => write!(f, "{}", byte as char)?,
+                _ => write!(f, "%{:02X}", byte)?,
+            }
+        }
+
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Dot segments are resolved after decoding, so `%2E%2E` can't be used to
+// climb out of the root. Encoded slashes are rejected for the same reason.
+fn normalize_path(path: &str) -> Result<String, Error> {
+    let mut segments: Vec<String> = Vec::new();
+    let mut trailing_slash = false;
+
+    for segment in path.split('/').skip(1) {
+        let segment = percent_decode(segment.as_bytes())
+            .and_then(|bytes| String::from_utf8(bytes.into_owned()).ok())
+            .filter(|segment| !segment.contains(['/', '\0']))
+            .ok_or(ErrorKind::InvalidRequestTarget)?;
+
+        trailing_slash = true;
+
+        match segment.as_str() {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => {
+                segments.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    let mut normalized = String::from("/");
+    normalized.push_str(&segments.join("/"));
+
+    if trailing_slash && !segments.is_empty() {
+        normalized.push('/');
+    }
+
+    Ok(normalized)
+}
+
+// `None` if a percent sign isn't followed by two hex digits
+fn percent_decode(input: &[u8]) -> Option<Cow<'_, [u8]>> {
+    if !input.contains(&b'%') {
+        return Some(Cow::Borrowed(input));
+    }
+
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut bytes = input.iter();
+
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'%' => decoded.push(hex_pair(&mut bytes)?),
+            _ => decoded.push(byte),
+        }
+    }
+
+    Some(Cow::Owned(decoded))
+}
+
+fn hex_pair(bytes: &mut slice::Iter<'_, u8>) -> Option<u8> {
+    let high = (*bytes.next()? as char).to_digit(16)?;
+    let low = (*bytes.next()? as char).to_digit(16)?;
+
+    Some((high * 16 + low) as u8)
+}
+
+// Handlers get something readable even for sloppy queries: malformed escapes
+// are kept as they are and invalid UTF-8 is replaced
+fn decode_form(input: &str) -> String {
+    let input = input.replace('+', " ");
+
+    match percent_decode(input.as_bytes()) {
+        Some(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        None => input,
+    }
+}
+
+/// Query parameters in the order they were sent. A name may appear more
+/// than once, and names are compared exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    params: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    /// The first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value)
+    }
+
+    /// All values of `name`, in the order they were sent.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |(param, _)| *param == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(input: &str) -> String {
+        Uri::parse(input).unwrap().path
+    }
+
+    #[test]
+    fn uri_parsing_splits_parts() {
+        let uri = Uri::parse("/hello?name=x").unwrap();
+
+        assert_eq!(uri.path, "/hello");
+        assert_eq!(uri.query.as_deref(), Some("name=x"));
+
+        let uri = Uri::parse("/hello").unwrap();
+        assert_eq!(uri, Uri::new("/hello"));
+        assert_eq!(Uri::parse("/a/../b").unwrap(), Uri::new("/a/../b"));
+
+        assert!(Uri::parse("/hello#top").is_err());
+        assert!(Uri::parse("/hello?name=x#top").is_err());
+    }
+
+    #[test]
+    fn uri_parsing_decodes_path() {
+        assert_eq!(path("/hello%20world"), "/hello world");
+        assert_eq!(path("/caf%C3%A9"), "/café");
+        assert_eq!(path("/a+b"), "/a+b");
+
+        assert!(Uri::parse("/a%2Fb").is_err());
+        assert!(Uri::parse("/a%00").is_err());
+        assert!(Uri::parse("/a%2").is_err());
+        assert!(Uri::parse("/a%zz").is_err());
+        assert!(Uri::parse("/%ff").is_err());
+    }
+
+    #[test]
+    fn uri_parsing_normalizes_path() {
+        assert_eq!(path("/"), "/");
+        assert_eq!(path("//a///b"), "/a/b");
+        assert_eq!(path("/a/./b/../c"), "/a/c");
+        assert_eq!(path("/a/b/.."), "/a/");
+        assert_eq!(path("/dir/"), "/dir/");
+        assert_eq!(path("/../../etc/passwd"), "/etc/passwd");
+        assert_eq!(path("/a/%2E%2E/%2e%2e/b"), "/b");
+    }
+
+    #[test]
+    fn uri_parsing_rejects_relative_path() {
+        assert_eq!(
+            Uri::parse("hello").unwrap_err().kind(),
+            ErrorKind::InvalidRequestTarget
+        );
+    }
+
+    #[test]
+    fn uri_display_encodes_path() {
+        let uri = Uri::parse("/hello%20w%C3%B6rld/a:b?x=%20").unwrap();
+
+        assert_eq!(uri.to_string(), "/hello%20w%C3%B6rld/a:b?x=%20");
+    }
+
+    #[test]
+    fn uri_segments() {
+        let uri = Uri::parse("/users/42/").unwrap();
+
+        assert_eq!(uri.segments().collect::<Vec<_>>(), vec!["users", "42"]);
+    }
+
+    #[test]
+    fn query_params() {
+        let uri = Uri::parse("/search?q=rust+http&tag=a&tag=b%26c&empty&&x=%zz").unwrap();
+
+        let params = uri.query_params();
+
+        assert_eq!(params.get("q"), Some("rust http"));
+        assert_eq!(params.get_all("tag").collect::<Vec<_>>(), vec!["a", "b&c"]);
+        assert_eq!(params.get("empty"), Some(""));
+        assert_eq!(params.get("x"), Some("%zz"));
+        assert_eq!(params.get("Q"), None);
+        assert_eq!(params.len(), 5);
+
+        assert!(Uri::new("/").query_params().is_empty());
+    }
+
+    fn target(input: &str, method: Method) -> Result<(RequestTarget, Uri), ErrorKind> {
+        RequestTarget::parse(input, &method).map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn request_target_forms() {
+        assert_eq!(
+            target("/where?q=now", Method::Get),
+            Ok((RequestTarget::Origin, Uri::parse("/where?q=now").unwrap()))
+        );
+        assert_eq!(
+            target("HTTP://www.example.org:8080/where?q=now", Method::Get),
+            Ok((
+                RequestTarget::Absolute {
+                    scheme: "http".to_string(),
+                    authority: "www.example.org:8080".parse().unwrap()
+                },
+                Uri::parse("/where?q=now").unwrap()
+            ))
+        );
+        assert_eq!(
+            target("http://www.example.org?q=now", Method::Get)
+                .unwrap()
+                .1,
+            Uri::parse("/?q=now").unwrap()
+        );
+        assert_eq!(
+            target("www.example.com:443", Method::Connect),
+            Ok((
+                RequestTarget::Authority("www.example.com:443".parse().unwrap()),
+                Uri::default()
+            ))
+        );
+        assert_eq!(
+            target("*", Method::Options),
+            Ok((RequestTarget::Asterisk, Uri::default()))
+        );
+    }
+
+    #[test]
+    fn request_target_must_fit_method() {
+        let invalid = Err(ErrorKind::InvalidRequestTarget);
+
+        assert_eq!(target("*", Method::Get), invalid);
+        assert_eq!(target("www.example.com:443", Method::Get), invalid);
+        assert_eq!(target("www.example.com", Method::Connect), invalid);
+        assert_eq!(target("/where", Method::Connect), invalid);
+        assert_eq!(target("1http://example.com/", Method::Get), invalid);
+        assert_eq!(target("http://user@example.com/", Method::Get), invalid);
+        assert_eq!(target("http:///where", Method::Get), invalid);
+        assert_eq!(target("http://example.com/where#top", Method::Get), invalid);
+    }
+}
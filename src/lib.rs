@@ -1,36 +1,228 @@
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
 
-use crossbeam::scope;
 use log::{debug, error, info};
 use std::{
-    env,
-    io::{self, prelude::*},
-    net::{TcpListener, TcpStream},
-    str,
-    sync::mpsc::{self, Receiver, TryRecvError},
+    env, io,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    thread,
     time::Duration,
 };
 
+mod clock;
+mod connection;
 pub mod http;
 pub mod middleware;
+mod net;
+pub mod parser_combinator;
+mod reactor;
 pub mod routing;
+pub mod server;
+#[cfg(feature = "tls")]
+mod tls;
 
+use connection::Connection;
 use http::{Response, ResponseClass, Status};
 use middleware::Middleware;
+use net::Stream;
+use server::{Server, Tracker};
+
+const DEFAULT_MAX_HEADER_SIZE: usize = 8 * 1024;
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_REQUESTS_PER_CONNECTION: usize = 100;
+const DEFAULT_WORKERS: usize = 4;
+const DEFAULT_QUEUE_SIZE: usize = 128;
+const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+// How long the rest of a request is discarded after answering it early
+const LINGER_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct UnixSocket {
+    path: PathBuf,
+    mode: u32,
+}
+
+#[cfg(feature = "tls")]
+struct TlsCertificate {
+    hostname: Option<String>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
 
 pub struct Config {
-    port: u16,
+    addresses: Vec<SocketAddr>,
+    unix_socket: Option<UnixSocket>,
+    #[cfg(feature = "tls")]
+    tls_addresses: Vec<SocketAddr>,
+    #[cfg(feature = "tls")]
+    tls_certificates: Vec<TlsCertificate>,
+    max_header_size: usize,
+    max_body_size: usize,
+    keep_alive_timeout: Duration,
+    max_requests_per_connection: usize,
+    workers: usize,
+    queue_size: usize,
+    shutdown_grace_period: Duration,
+    detailed_errors: bool,
+    server_header: Option<String>,
 }
 
 impl Config {
+    /// Listens on the IPv4 loopback interface at the given port.
     pub fn new(port: u16) -> Config {
-        Config { port }
+        Config::with_addresses(vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))])
     }
 
+    /// Listens on all of the given addresses, e.g. `0.0.0.0:80` and `[::]:80`.
+    pub fn with_addresses(addresses: Vec<SocketAddr>) -> Config {
+        Config {
+            addresses,
+            unix_socket: None,
+            #[cfg(feature = "tls")]
+            tls_addresses: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls_certificates: Vec::new(),
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            keep_alive_timeout: DEFAULT_KEEP_ALIVE_TIMEOUT,
+            max_requests_per_connection: DEFAULT_MAX_REQUESTS_PER_CONNECTION,
+            workers: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(DEFAULT_WORKERS),
+            queue_size: DEFAULT_QUEUE_SIZE,
+            shutdown_grace_period: DEFAULT_SHUTDOWN_GRACE_PERIOD,
+            detailed_errors: false,
+            server_header: Some(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )),
+        }
+    }
+
+    /// Every argument is either a port on the loopback interface or a full
+    /// socket address. At least one has to be given.
     pub fn from_args() -> Config {
-        let args: Vec<String> = env::args().collect();
-        let port = u16::from_str_radix(&args[1], 10).expect("Supplied port is invalid");
-        Config { port }
+        let addresses: Vec<SocketAddr> = env::args()
+            .skip(1)
+            .map(|arg| match arg.parse::<u16>() {
+                Ok(port) => SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+                Err(_) => arg.parse().expect("Supplied address is invalid"),
+            })
+            .collect();
+
+        if addresses.is_empty() {
+            panic!("No port or address supplied");
+        }
+
+        Config::with_addresses(addresses)
+    }
+
+    /// Additionally listens on a Unix domain socket at `path`, with the
+    /// socket file's permissions set to `mode`, e.g. `0o660`.
+    pub fn unix_socket<P: Into<PathBuf>>(mut self, path: P, mode: u32) -> Config {
+        self.unix_socket = Some(UnixSocket {
+            path: path.into(),
+            mode,
+        });
+        self
+    }
+
+    /// Additionally listens for HTTPS connections on the given addresses.
+    /// Requires at least one certificate.
+    #[cfg(feature = "tls")]
+    pub fn tls_addresses(mut self, addresses: Vec<SocketAddr>) -> Config {
+        self.tls_addresses = addresses;
+        self
+    }
+
+    /// Loads the PEM encoded certificate chain and private key used for
+    /// clients that don't ask for a hostname with a certificate of its own.
+    #[cfg(feature = "tls")]
+    pub fn tls_certificate<P: Into<PathBuf>>(mut self, cert_path: P, key_path: P) -> Config {
+        self.tls_certificates.push(TlsCertificate {
+            hostname: None,
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        });
+        self
+    }
+
+    /// Loads the PEM encoded certificate chain and private key used for
+    /// clients asking for `hostname` through SNI.
+    #[cfg(feature = "tls")]
+    pub fn tls_sni_certificate<P: Into<PathBuf>>(
+        mut self,
+        hostname: &str,
+        cert_path: P,
+        key_path: P,
+    ) -> Config {
+        self.tls_certificates.push(TlsCertificate {
+            hostname: Some(hostname.to_string()),
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        });
+        self
+    }
+
+    /// Requests whose request line and headers exceed this many bytes are
+    /// answered with `431 Request Header Fields Too Large`.
+    pub fn max_header_size(mut self, size: usize) -> Config {
+        self.max_header_size = size;
+        self
+    }
+
+    /// Requests whose body exceeds this many bytes are answered with
+    /// `413 Content Too Large`.
+    pub fn max_body_size(mut self, size: usize) -> Config {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Persistent connections are closed after being idle for this long.
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Config {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Persistent connections are closed after serving this many requests.
+    pub fn max_requests_per_connection(mut self, max: usize) -> Config {
+        self.max_requests_per_connection = max;
+        self
+    }
+
+    /// Number of worker threads handling connections.
+    pub fn workers(mut self, workers: usize) -> Config {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Number of accepted connections that may wait for a free worker.
+    /// Connections beyond that are answered with `503 Service Unavailable`.
+    pub fn queue_size(mut self, size: usize) -> Config {
+        self.queue_size = size;
+        self
+    }
+
+    /// How long active requests may take to finish after a shutdown was
+    /// requested before their connections are closed forcibly.
+    pub fn shutdown_grace_period(mut self, grace_period: Duration) -> Config {
+        self.shutdown_grace_period = grace_period;
+        self
+    }
+
+    /// Explains what was wrong with requests that couldn't be parsed in the
+    /// body of the error response. Meant for debugging.
+    pub fn detailed_errors(mut self, enabled: bool) -> Config {
+        self.detailed_errors = enabled;
+        self
+    }
+
+    /// The value of the `Server` header sent with every response, or `None`
+    /// to leave it out. Defaults to the crate's name and version.
+    pub fn server_header(mut self, value: Option<&str>) -> Config {
+        self.server_header = value.map(str::to_string);
+        self
     }
 }
 
@@ -44,83 +236,94 @@ impl Application {
     }
 
     pub fn run(&self, config: Config) {
-        let (_tx, rx) = mpsc::channel();
-
-        self.run_graceful(config, rx);
+        self.bind(config).expect("Could not start server").serve();
     }
 
-    pub fn run_graceful(&self, config: Config, receiver: Receiver<()>) {
-        info!(
-            "Starting {} {}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION")
-        );
+    /// Binds the configured addresses. Serving starts with `Server::serve`.
+    pub fn bind(&self, config: Config) -> io::Result<Server<'_>> {
+        Server::bind(self, config)
+    }
 
-        let address = format!("127.0.0.1:{}", config.port);
-
-        info!("Listening at: {}", address);
-        let listener = TcpListener::bind(address).unwrap();
-        listener
-            .set_nonblocking(true)
-            .expect("Could not set listener to non-blocking");
-
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    debug!("Handling new stream");
-
-                    scope(|s| {
-                        s.spawn(move |_| {
-                            self.handle_client(stream);
-                        });
-                    })
-                    .unwrap();
-                }
-                Err(ref e) => {
-                    if e.kind() == io::ErrorKind::WouldBlock {
-                        std::thread::sleep(Duration::from_millis(100));
-                    }
+    /// Answers `req` in the HTTP version it was sent in. Middleware that
+    /// don't handle `HEAD` themselves answer it like `GET`, without the
+    /// body.
+    pub fn respond_to(&self, req: &http::Request) -> Response {
+        let mut response = self
+            .dispatch_to_middleware(req)
+            .unwrap_or_else(|e| match e {
+                // No middleware knows what to do with the method
+                _ if matches!(req.method, http::Method::Extension(_))
+                    && !self.supports_method(&req.method) =>
+                {
+                    Response::new(Status::NotImplemented)
                 }
-            }
-
-            match receiver.try_recv() {
-                Ok(_) => {
-                    info!("Shutting down");
-                    break;
+                middleware::Error::MethodNotAllowed => Response::new(Status::MethodNotAllowed),
+                // Options for the server as a whole, of which there are none
+                middleware::Error::NotFound if req.target == http::RequestTarget::Asterisk => {
+                    Response::new(Status::Ok)
                 }
-                Err(TryRecvError::Disconnected) => {
-                    debug!("Application's termination channel disconnected");
+                // Tunnels are up to proxies
+                middleware::Error::NotFound if req.method == http::Method::Connect => {
+                    Response::new(Status::MethodNotAllowed)
                 }
-                Err(TryRecvError::Empty) => {}
-            }
+                middleware::Error::NotFound => Response::new(Status::NotFound),
+            });
+
+        response.version = req.version;
+
+        match req.method {
+            http::Method::Head => response.without_body(),
+            _ => response,
         }
     }
 
-    pub fn respond_to(&self, req: &http::Request) -> Response {
-        self.dispatch_to_middleware(&req)
-            .unwrap_or_else(|e| match e {
-                middleware::Error::MethodNotAllowed => Response::new(Status::MethodNotAllowed),
-                middleware::Error::NotFound => Response::new(Status::NotFound),
-            })
+    /// Gives every middleware a chance to answer `req` from its header
+    /// alone, before the body is read.
+    pub fn precheck(&self, req: &http::Request) -> Option<Response> {
+        let mut response = self
+            .middleware
+            .iter()
+            .find_map(|current| current.precheck(req))?;
+
+        response.version = req.version;
+        Some(response)
     }
 
-    pub fn respond_to_str(&self, req_str: &str) -> Response {
-        match http::Request::parse(req_str) {
+    pub fn respond_to_bytes(&self, req_bytes: &[u8]) -> Response {
+        match http::Request::parse(req_bytes) {
             Ok(req) => self.respond_to(&req),
-            Err(e) => match e {
-                http::Error::UnsupportedVersion => Response::new(Status::VersionNotSupported),
-                http::Error::UnknownMethod => Response::new(Status::BadRequest),
-                http::Error::MalformedRequest => Response::new(Status::BadRequest),
-            },
+            Err(e) => respond_to_parse_error(&e, false),
         }
     }
 
+    pub fn respond_to_str(&self, req_str: &str) -> Response {
+        self.respond_to_bytes(req_str.as_bytes())
+    }
+
+    fn supports_method(&self, method: &http::Method) -> bool {
+        self.middleware
+            .iter()
+            .any(|current| current.supports_method(method))
+    }
+
     fn dispatch_to_middleware(
         &self,
         request: &http::Request,
     ) -> Result<http::Response, middleware::Error> {
+        // Middleware without HEAD handling of their own answer it like GET,
+        // which is cheaper than dispatching the request twice
+        let get = match request.method {
+            http::Method::Head => Some(request.head_as_get()),
+            _ => None,
+        };
+
         for current in &self.middleware {
-            match current.answer(&request) {
+            let request = match &get {
+                Some(get) if !current.handles_head(request) => get,
+                _ => request,
+            };
+
+            match current.answer(request) {
                 Err(middleware::Error::NotFound) => continue,
                 res => return res,
             };
@@ -129,29 +332,118 @@ impl Application {
         Err(middleware::Error::NotFound)
     }
 
-    fn handle_client(&self, mut stream: TcpStream) {
-        let mut buffer = [0; 1024];
-        let _ = stream.read(&mut buffer[..]).unwrap();
+    fn handle_client(&self, stream: Stream, config: &Config, tracker: &Tracker) {
+        if let Err(e) = stream.set_read_timeout(Some(config.keep_alive_timeout)) {
+            error!("Could not set read timeout: {}", e);
+            return;
+        }
+
+        let mut connection = Connection::new(stream);
+        let mut served = 0;
+
+        loop {
+            if !tracker.wait_for_request() {
+                return;
+            }
+
+            // The connection is busy from the first byte on, so shutting
+            // down doesn't cut off requests that are still being received
+            match connection.wait_for_data() {
+                Ok(true) => tracker.start_request(),
+                Ok(false) => return,
+                Err(e) => {
+                    debug!("Closing connection: {}", e);
+                    return;
+                }
+            }
+
+            let request = connection.read_request(config, |head| self.precheck(head));
+            let failed = request.is_err();
+
+            let (summary, mut response, keep_alive) = match request {
+                Ok(Some(request)) => {
+                    served += 1;
+
+                    let summary = format!(
+                        "{} {} {}",
+                        request.method, request.uri.path, request.version
+                    );
+                    let keep_alive =
+                        request.keep_alive() && served < config.max_requests_per_connection;
+
+                    (summary, self.respond_to(&request), keep_alive)
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    let summary = e.to_string();
+                    let response = match e {
+                        connection::Error::Io(e) => {
+                            debug!("Closing connection: {}", e);
+                            return;
+                        }
+                        connection::Error::Http(ref e) => {
+                            respond_to_parse_error(e, config.detailed_errors)
+                        }
+                        connection::Error::Rejected(response) => *response,
+                    };
+
+                    // The rest of the stream can't be trusted after an error
+                    (summary, response, false)
+                }
+            };
+
+            let keep_alive = keep_alive && response.keep_alive() && !tracker.is_shutting_down();
+
+            if !keep_alive {
+                response = response.header(("Connection", "close"));
+            } else if response.version == http::Version::OneDotZero {
+                // Persistence is opt-in for HTTP/1.0, so it is confirmed
+                response = response.header(("Connection", "keep-alive"));
+            }
 
-        let req_str = str::from_utf8(&buffer).unwrap();
-        let request = String::from(req_str);
+            let s = format!("{} => {}", summary, response.status);
 
-        // TODO
-        let request_copy = request.clone();
-        let first_line = request_copy.splitn(2, "\r\n").next().unwrap();
-        let response = self.respond_to_str(&request);
+            match response.class() {
+                ResponseClass::Informational => info!("{}", s),
+                ResponseClass::Successful => info!("{}", s),
+                ResponseClass::Redirection => info!("{}", s),
+                ResponseClass::ClientError => error!("{}", s),
+                ResponseClass::ServerError => error!("{}", s),
+            }
 
-        let s = format!("{} => {}", first_line, response.status);
+            if let Err(e) = connection.write_response(response, config) {
+                debug!("Could not write response: {}", e);
+                return;
+            }
+
+            // The client may still be sending the rest of a failed request,
+            // e.g. a body it stopped waiting for `100 Continue` for
+            if failed {
+                connection.into_inner().linger(LINGER_TIMEOUT);
+                return;
+            }
 
-        match response.class() {
-            ResponseClass::Informational => info!("{}", s),
-            ResponseClass::Successful => info!("{}", s),
-            ResponseClass::Redirection => info!("{}", s),
-            ResponseClass::ClientError => error!("{}", s),
-            ResponseClass::ServerError => error!("{}", s),
+            if !keep_alive {
+                return;
+            }
         }
+    }
+}
+
+fn respond_to_parse_error(error: &http::Error, detailed: bool) -> Response {
+    let status = match error.kind() {
+        http::ErrorKind::UnsupportedVersion => Status::VersionNotSupported,
+        http::ErrorKind::HeaderTooLarge => Status::RequestHeaderFieldsTooLarge,
+        http::ErrorKind::BodyTooLarge => Status::ContentTooLarge,
+        _ => Status::BadRequest,
+    };
 
-        let _ = stream.write(format!("{}", response).as_bytes());
+    let response = Response::new(status);
+
+    if detailed {
+        response.body(format!("{}\n", error), mime::TEXT_PLAIN)
+    } else {
+        response
     }
 }
 
@@ -159,7 +451,13 @@ impl Application {
 mod tests {
     use super::*;
     use http::Request;
-    use middleware::MockMiddleware;
+    use middleware::{FileMiddleware, MockMiddleware};
+    use std::str;
+
+    // HTTP/1.1 requests without a body, which only need a Host header
+    fn with_host(request_line: &str) -> Vec<u8> {
+        format!("{}\r\nHost: example.com\r\n\r\n", request_line).into_bytes()
+    }
 
     #[test]
     fn mytest() {
@@ -229,13 +527,163 @@ mod tests {
         assert_eq!(res.status, Status::NotFound);
     }
 
+    #[test]
+    fn respond_to_answers_unhandled_extension_methods() {
+        let mut router = routing::Router::new();
+        router.register("/test", http::Method::Get, |_| Response::new(Status::Ok));
+        router.register(
+            "/dav",
+            http::Method::Extension("PROPFIND".to_string()),
+            |_| Response::new(Status::MultiStatus),
+        );
+        let application = Application::new(vec![Box::new(router)]);
+
+        let res = application.respond_to_bytes(&with_host("PROPFIND /dav HTTP/1.1"));
+        assert_eq!(res.status, Status::MultiStatus);
+
+        // Another path supports the method
+        let res = application.respond_to_bytes(&with_host("PROPFIND /test HTTP/1.1"));
+        assert_eq!(res.status, Status::MethodNotAllowed);
+
+        let res = application.respond_to_bytes(&with_host("PROPFIND /missing HTTP/1.1"));
+        assert_eq!(res.status, Status::NotFound);
+
+        let res = application.respond_to_bytes(&with_host("MKCOL /test HTTP/1.1"));
+        assert_eq!(res.status, Status::NotImplemented);
+
+        let res = application.respond_to_bytes(&with_host("PATCH /test HTTP/1.1"));
+        assert_eq!(res.status, Status::MethodNotAllowed);
+    }
+
+    #[test]
+    fn respond_to_answers_unhandled_methods_on_files() {
+        let mut router = routing::Router::new();
+        router.register("/test", http::Method::Get, |_| Response::new(Status::Ok));
+        let application = Application::new(vec![
+            Box::new(router),
+            Box::new(FileMiddleware::new("tests/mock")),
+        ]);
+
+        let res = application.respond_to_bytes(&with_host("GET /test_one/test.html HTTP/1.1"));
+        assert_eq!(res.status, Status::Ok);
+
+        for method in &["POST", "DELETE", "PATCH"] {
+            let request_line = format!("{} /test_one/test.html HTTP/1.1", method);
+            let res = application.respond_to_bytes(&with_host(&request_line));
+            assert_eq!(res.status, Status::MethodNotAllowed);
+        }
+
+        let res = application.respond_to_bytes(&with_host("MKCOL /test_one/test.html HTTP/1.1"));
+        assert_eq!(res.status, Status::NotImplemented);
+
+        let res = application.respond_to_bytes(&with_host("DELETE /missing HTTP/1.1"));
+        assert_eq!(res.status, Status::NotFound);
+    }
+
+    #[test]
+    fn respond_to_answers_head_like_get() {
+        let mut router = routing::Router::new();
+        router.register("/test", http::Method::Get, |_| {
+            Response::new(Status::Ok).body("Hello, test!", mime::TEXT_PLAIN)
+        });
+        router.register("/stream", http::Method::Get, |_| {
+            let body = http::Body::stream(&b"Hello, stream!"[..], None);
+            Response::new(Status::Ok).body(body, mime::TEXT_PLAIN)
+        });
+        router.register("/own", http::Method::Head, |_| {
+            Response::new(Status::NoContent)
+        });
+        let application = Application::new(vec![Box::new(router)]);
+
+        let res = application.respond_to_bytes(&with_host("HEAD /test HTTP/1.1"));
+        assert_eq!(res.status, Status::Ok);
+        assert_eq!(res.header_value("Content-Length"), Some("12"));
+        assert_eq!(res.header_value("Content-Type"), Some("text/plain"));
+        assert!(res.to_bytes().unwrap().ends_with(b"\r\n\r\n"));
+
+        // The length of a streamed body isn't known
+        let res = application.respond_to_bytes(&with_host("HEAD /stream HTTP/1.1"));
+        let bytes = res.to_bytes().unwrap();
+        let head = str::from_utf8(&bytes).unwrap();
+        assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+        assert!(head.ends_with("\r\n\r\n"));
+
+        // HTTP/1.0 has no chunked encoding to announce
+        let res = application.respond_to_bytes(b"HEAD /stream HTTP/1.0\r\n\r\n");
+        let bytes = res.to_bytes().unwrap();
+        let head = str::from_utf8(&bytes).unwrap();
+        assert!(!head.contains("Transfer-Encoding"));
+        assert!(!head.contains("Content-Length"));
+
+        let res = application.respond_to_bytes(&with_host("HEAD /own HTTP/1.1"));
+        assert_eq!(res.status, Status::NoContent);
+
+        let res = application.respond_to_bytes(&with_host("HEAD /missing HTTP/1.1"));
+        assert_eq!(res.status, Status::NotFound);
+    }
+
+    #[test]
+    fn respond_to_dispatches_head_once_as_get() {
+        let mut mock = MockMiddleware::new();
+        mock.expect_handles_head().times(1).return_const(false);
+        mock.expect_answer()
+            .times(1)
+            .withf(|request| request.method == http::Method::Get)
+            .returning(|_| Ok(Response::new(Status::Ok).body("Hello", mime::TEXT_PLAIN)));
+        let application = Application::new(vec![Box::new(mock)]);
+
+        let mut req = Request::get("/");
+        req.method = http::Method::Head;
+
+        let res = application.respond_to(&req);
+        assert_eq!(res.status, Status::Ok);
+        assert_eq!(res.header_value("Content-Length"), Some("5"));
+        assert!(res.to_bytes().unwrap().ends_with(b"\r\n\r\n"));
+    }
+
+    #[test]
+    fn respond_to_answers_other_target_forms() {
+        let application = Application::new(vec![Box::new(FileMiddleware::new("tests/mock"))]);
+
+        let res = application.respond_to_bytes(&with_host("OPTIONS * HTTP/1.1"));
+        assert_eq!(res.status, Status::Ok);
+
+        let res = application.respond_to_bytes(&with_host("CONNECT example.com:443 HTTP/1.1"));
+        assert_eq!(res.status, Status::MethodNotAllowed);
+
+        let res =
+            application.respond_to_bytes(&with_host("GET http://example.com/test_one/ HTTP/1.1"));
+        assert_eq!(res.status, Status::Ok);
+    }
+
+    #[test]
+    fn bind_fails_without_addresses() {
+        let application = Application::new(vec![]);
+
+        let result = application.bind(Config::with_addresses(Vec::new()));
+
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+    }
+
     #[test]
     fn respond_to_str_returns_error_responses() {
         let application = Application::new(vec![]);
 
-        // TODO: Fix parsing problem with "\r\n\r\n"
-        let req_str = "GET /test HTTP/2.0\r\n\r\n";
-        let res = application.respond_to_str(req_str);
+        let res = application.respond_to_str("GET /test HTTP/2.0\r\n\r\n");
+
+        assert_eq!(res.status, Status::VersionNotSupported);
+    }
+
+    #[test]
+    fn respond_to_bytes_returns_error_responses() {
+        let application = Application::new(vec![]);
+
+        let req_bytes = b"GET /test HTTP/2.0\r\n\r\n";
+        let res = application.respond_to_bytes(req_bytes);
 
         assert_eq!(res.status, Status::VersionNotSupported);
     }
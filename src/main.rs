@@ -1,5 +1,3 @@
-use mime;
-use pretty_env_logger;
 use turbo_bernd::{
     http,
     middleware::{FileMiddleware, Middleware},
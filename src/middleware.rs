@@ -1,9 +1,8 @@
 use crate::http;
 
-use mime;
 #[cfg(test)]
 use mockall::automock;
-use std::{fmt, fs::File, io::prelude::*, path::Path};
+use std::{fmt, fs::File, path::Path};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -25,6 +24,27 @@ impl fmt::Display for Error {
 #[cfg_attr(test, automock)]
 pub trait Middleware: Sync {
     fn answer(&self, request: &http::Request) -> Result<http::Response, Error>;
+
+    /// Whether the middleware answers requests with `method` on any path.
+    /// Extension methods none of them support are answered with
+    /// `501 Not Implemented` rather than `404` or `405`.
+    fn supports_method(&self, _method: &http::Method) -> bool {
+        false
+    }
+
+    /// Whether the middleware answers the `HEAD` `request` itself. Otherwise
+    /// it is handed the request as `GET` and the body is left out of the
+    /// response.
+    fn handles_head(&self, _request: &http::Request) -> bool {
+        false
+    }
+
+    /// Looks at a request that waits for `100 Continue` before its body was
+    /// read. Returning a response, e.g. `401 Unauthorized`, answers the
+    /// request without ever reading the body.
+    fn precheck(&self, _request: &http::Request) -> Option<http::Response> {
+        None
+    }
 }
 
 pub struct FileMiddleware<'a> {
@@ -32,20 +52,41 @@ pub struct FileMiddleware<'a> {
 }
 
 impl<'a> FileMiddleware<'a> {
-    pub fn new(file_directory: &str) -> FileMiddleware {
+    pub fn new(file_directory: &str) -> FileMiddleware<'_> {
         FileMiddleware { file_directory }
     }
 }
 
 impl<'a> Middleware for FileMiddleware<'a> {
+    fn supports_method(&self, method: &http::Method) -> bool {
+        matches!(method, http::Method::Get | http::Method::Head)
+    }
+
+    fn handles_head(&self, _request: &http::Request) -> bool {
+        true
+    }
+
     fn answer(&self, request: &http::Request) -> Result<http::Response, Error> {
-        let mut buffer = String::new();
+        // Only the origin and absolute forms name a path
+        if let http::RequestTarget::Authority(_) | http::RequestTarget::Asterisk = request.target {
+            return Err(Error::NotFound);
+        }
 
         let file_path = format!("{}{}", self.file_directory, request.uri.path);
 
         let path = Path::new(&file_path);
 
-        if path.is_dir() {
+        // Files can only be read, what isn't there is left to other middleware
+        if !self.supports_method(&request.method) {
+            return match path.exists() {
+                true => Err(Error::MethodNotAllowed),
+                false => Err(Error::NotFound),
+            };
+        }
+
+        let (body, mime) = if path.is_dir() {
+            let mut buffer = String::new();
+
             buffer.push_str("<html>");
             buffer.push_str("<body>");
 
@@ -73,15 +114,53 @@ impl<'a> Middleware for FileMiddleware<'a> {
 
             buffer.push_str("</body>");
             buffer.push_str("</html>");
+
+            (http::Body::from(buffer), mime::TEXT_HTML)
+        } else if request.method == http::Method::Head {
+            // if err: problem with file metadata
+            let length = path.metadata().or(Err(Error::NotFound))?.len();
+
+            // Responses to HEAD have no body, so the file doesn't need to be
+            // opened
+            let response = http::Response::new(http::Status::Ok)
+                .header(("Content-Length", &length.to_string()))
+                .header(("Content-Type", content_type(path).essence_str()));
+
+            return Ok(response);
         } else {
-            let _ = match File::open(file_path) {
-                Ok(mut f) => f.read_to_string(&mut buffer),
-                Err(_) => return Err(Error::NotFound),
-            };
-        }
+            // if err: problem opening file
+            let file = File::open(path).or(Err(Error::NotFound))?;
 
-        let response = http::Response::new(http::Status::Ok).body(&buffer, mime::TEXT_HTML);
+            // if err: problem with file metadata
+            let length = file.metadata().or(Err(Error::NotFound))?.len();
+
+            // The file is read while the response is being sent
+            (http::Body::stream(file, Some(length)), content_type(path))
+        };
+
+        let response = http::Response::new(http::Status::Ok).body(body, mime);
 
         Ok(response)
     }
 }
+
+fn content_type(path: &Path) -> mime::Mime {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("html") | Some("htm") => mime::TEXT_HTML,
+        Some("css") => mime::TEXT_CSS,
+        Some("js") => mime::APPLICATION_JAVASCRIPT,
+        Some("json") => mime::APPLICATION_JSON,
+        Some("txt") => mime::TEXT_PLAIN,
+        Some("png") => mime::IMAGE_PNG,
+        Some("jpg") | Some("jpeg") => mime::IMAGE_JPEG,
+        Some("gif") => mime::IMAGE_GIF,
+        Some("svg") => mime::IMAGE_SVG,
+        Some("pdf") => mime::APPLICATION_PDF,
+        _ => mime::APPLICATION_OCTET_STREAM,
+    }
+}
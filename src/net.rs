@@ -0,0 +1,329 @@
+use std::{
+    fmt, fs,
+    io::{self, prelude::*},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{AsRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tls")]
+use log::error;
+#[cfg(feature = "tls")]
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+// Peers that don't read get no longer than this to take the close_notify
+#[cfg(feature = "tls")]
+const CLOSE_NOTIFY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A socket the server accepts connections on.
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener, PathBuf),
+    #[cfg(feature = "tls")]
+    Tls(TcpListener, Arc<ServerConfig>),
+}
+
+impl Listener {
+    /// Binds a Unix domain socket at `path` and sets its permissions to
+    /// `mode`. A socket left behind by a server that is no longer running is
+    /// removed first.
+    pub fn bind_unix(path: &Path, mode: u32) -> io::Result<Listener> {
+        remove_stale_socket(path)?;
+
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+
+        Ok(Listener::Unix(listener, path.to_path_buf()))
+    }
+
+    pub fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(s, _)| Stream::Tcp(s)),
+            Listener::Unix(listener, _) => listener.accept().map(|(s, _)| Stream::Unix(s)),
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, config) => loop {
+                let (stream, _) = listener.accept()?;
+
+                // The handshake happens on the first read or write. A
+                // session that can't be set up only costs this connection,
+                // the ones queued behind it are still accepted.
+                match ServerConnection::new(Arc::clone(config)) {
+                    Ok(connection) => {
+                        return Ok(Stream::Tls(Box::new(StreamOwned::new(connection, stream))))
+                    }
+                    Err(e) => error!("Could not set up TLS session: {}", e),
+                }
+            },
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.set_nonblocking(nonblocking),
+            Listener::Unix(listener, _) => listener.set_nonblocking(nonblocking),
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, _) => listener.set_nonblocking(nonblocking),
+        }
+    }
+
+    /// The bound address of TCP listeners.
+    pub fn local_addr(&self) -> Option<io::Result<SocketAddr>> {
+        match self {
+            Listener::Tcp(listener) => Some(listener.local_addr()),
+            Listener::Unix(_, _) => None,
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, _) => Some(listener.local_addr()),
+        }
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Listener::Tcp(listener) => listener.as_raw_fd(),
+            Listener::Unix(listener, _) => listener.as_raw_fd(),
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, _) => listener.as_raw_fd(),
+        }
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(address) => write!(f, "{}", address),
+                Err(_) => write!(f, "unknown address"),
+            },
+            Listener::Unix(_, path) => write!(f, "unix:{}", path.display()),
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, _) => match listener.local_addr() {
+                Ok(address) => write!(f, "https://{}", address),
+                Err(_) => write!(f, "unknown address"),
+            },
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix(_, path) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A connection accepted from a `Listener`.
+pub enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl Stream {
+    /// Clones the underlying socket. The clone of a TLS stream bypasses
+    /// encryption and is only good for shutting the connection down.
+    pub fn try_clone_socket(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref().try_clone().map(Stream::Tcp),
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(how),
+            Stream::Unix(stream) => stream.shutdown(how),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref().shutdown(how),
+        }
+    }
+
+    /// Stops writing and discards what the peer still sends for at most
+    /// `timeout`. Closing a socket with unread data resets the connection,
+    /// which can discard a response before the peer reads it.
+    pub fn linger(&mut self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let _ = self.shutdown(Shutdown::Write);
+
+        let mut buffer = [0; 1024];
+
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if remaining.is_zero() || self.set_read_timeout(Some(remaining)).is_err() {
+                break;
+            }
+
+            match self.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            Stream::Unix(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref().set_nonblocking(nonblocking),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            Stream::Unix(stream) => stream.set_read_timeout(timeout),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref().set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_write_timeout(timeout),
+            Stream::Unix(stream) => stream.set_write_timeout(timeout),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref().set_write_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            Stream::Unix(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            Stream::Unix(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            Stream::Unix(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+// Lets TLS clients tell a complete response from a truncated one
+#[cfg(feature = "tls")]
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if let Stream::Tls(stream) = self {
+            if stream.conn.is_handshaking() {
+                return;
+            }
+
+            stream.conn.send_close_notify();
+
+            if stream
+                .sock
+                .set_write_timeout(Some(CLOSE_NOTIFY_TIMEOUT))
+                .is_err()
+            {
+                return;
+            }
+
+            while stream.conn.wants_write() {
+                if stream.conn.write_tls(&mut stream.sock).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Only sockets nobody is listening on are removed, anything else at the
+// path is left alone and makes binding fail
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+
+    match UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another server", path.display()),
+        )),
+        Err(_) => fs::remove_file(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn socket_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("turbo_bernd-{}-{}.sock", process::id(), name))
+    }
+
+    #[test]
+    fn bind_unix_sets_permissions() {
+        let path = socket_path("permissions");
+        let _listener = Listener::bind_unix(&path, 0o600).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn bind_unix_replaces_stale_socket() {
+        let path = socket_path("stale");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = Listener::bind_unix(&path, 0o660).unwrap();
+        UnixStream::connect(&path).unwrap();
+        listener.accept().unwrap();
+    }
+
+    #[test]
+    fn bind_unix_keeps_active_socket() {
+        let path = socket_path("active");
+        let _active = UnixListener::bind(&path).unwrap();
+
+        let result = Listener::bind_unix(&path, 0o660);
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::AddrInUse);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn dropping_unix_listener_removes_socket() {
+        let path = socket_path("drop");
+        drop(Listener::bind_unix(&path, 0o660).unwrap());
+
+        assert!(!path.exists());
+    }
+}
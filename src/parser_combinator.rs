@@ -1,434 +1,426 @@
-// // https://bodil.lol/parser-combinators/
-
-// #![type_length_limit = "16777216"]
-// use std::ops::RangeBounds;
-
-// #[derive(Clone, Debug, PartialEq, Eq)]
-// struct Element {
-//     name: String,
-//     attributes: Vec<(String, String)>,
-//     children: Vec<Element>,
-// }
-
-// //Fn(&str) -> Result<(&str, Element), &str>
-
-// // method request-target version
-// // GET / HTTP/1.1
-
-// // request_line = zip(method, sp, request_target, sp, http_version, crlf)
-// // status_line = zip(version, sp, status_code, sp, status_message, crlf)
-
-// // header = zip(string, colon, string, crlf)
-// // body = zero_or_more(byte)
-
-// // case-insensitive field name, ":", zero_or_more(whitespace), field value, zero_or_more(whitespace)
-
-// // request = zip(request_line, zero_or_more(header), crlf, body)
-// // response = zip(status_line, zero_or_more(header), crlf, body)
-
-// #[derive(Debug)]
-// enum Method {
-//     Get,
-//     Head,
-//     Post,
-//     Put,
-//     Delete,
-//     Connect,
-//     Options,
-//     Trace,
-// }
-
-// fn method(input: &str) -> ParseResult<Method> {
-//     loop {
-//         match input {
-
-//         }
-//     }
-// }
-
-// fn zip2<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, (R1, R2)>
-// where
-//     P1: Parser<'a, R1>,
-//     P2: Parser<'a, R2>,
-// {
-//     move |input| {
-//         parser1.parse(input).and_then(|(rest1, result1)| {
-//             parser2
-//                 .parse(rest1)
-//                 .map(|(rest2, result2)| (rest2, (result1, result2)))
-//         })
-//     }
-// }
-
-// fn zip3<'a, P1, P2, P3, R1, R2, R3>(
-//     parser1: P1,
-//     parser2: P2,
-//     parser3: P3,
-// ) -> impl Parser<'a, (R1, R2, R3)>
-// where
-//     P1: Parser<'a, R1>,
-//     P2: Parser<'a, R2>,
-//     P3: Parser<'a, R3>,
-// {
-//     move |input| {
-//         parser1.parse(input).and_then(|(rest1, result1)| {
-//             zip2(&parser2, &parser3)
-//                 .parse(rest1)
-//                 .map(|(rest2, (result2, result3))| {
-//                     (rest2, (result1, result2, result3))
-//                 })
-//         })
-//     }
-// }
-
-// fn map<P, F, A, B>(parser: P, function: F) -> impl Parser<B>
-// where
-//     P: Parser<A>,
-//     F: Fn(A) -> B,
-// {
-//     move |input| {
-//         parser
-//             .parse(input)
-//             .map(|(rest, result)| (rest, function(result)))
-//     }
-// }
-
-// fn pred<'a, P, A, F>(parser: P, predicate: F) -> impl Parser<'a, A>
-// where
-//     P: Parser<'a, A>,
-//     F: Fn(&A) -> bool,
-// {
-//     move |input| {
-//         if let Ok((rest, value)) = parser.parse(input) {
-//             if predicate(&value) {
-//                 return Ok((rest, value));
-//             }
-//         }
-//         Err(input)
-//     }
-// }
-
-// type ParseResult<'a, Output> = Result<(&'a str, Output), &'a str>;
-
-// trait Parser<'a, Output> {
-//     fn parse(&self, input: &'a str) -> ParseResult<'a, Output>;
-
-//     fn map<F, NewOutput>(self, function: F) -> BoxedParser<'a, NewOutput>
-//     where
-//         Self: Sized + 'a,
-//         Output: 'a,
-//         NewOutput: 'a,
-//         F: Fn(Output) -> NewOutput + 'a,
-//     {
-//         BoxedParser::new(map(self, function))
-//     }
-
-//     fn pred<F>(Self, predicate: F) -> BoxedParser<'a, Output>
-//     where
-//         Self: Sized + 'a,
-//         Output: 'a,
-//         F: Fn(&Output) -> bool + 'a,
-//     {
-//         BoxedParser::new(pred(self, predicate))
-//     }
-// }
-
-// // any closure that takes a &str and returns a ParseResult<Output> is a Parser<Output>
-// // and calling some_parser.parse(input) is like doing some_parser()
-// impl<'a, F, Output> Parser<'a, Output> for F
-// where
-//     F: Fn(&'a str) -> ParseResult<Output>,
-// {
-//     fn parse(&self, input: &'a str) -> ParseResult<'a, Output> {
-//         self(input)
-//     }
-// }
-
-// struct BoxedParser<'a, Output> {
-//     parser: Box<dyn Parser<'a, Output> + 'a>,
-// }
-
-// impl<'a, Output> BoxedParser<'a, Output> {
-//     fn new<P>(parser: P) -> self
-//     where
-//         P: Parser<'a, Output> + 'a,
-//     {
-//         BoxedParser {
-//             parser: Box::new(parser),
-//         }
-//     }
-// }
-
-// impl<'a, Output> Parser<'a, Output> for BoxedParser<'a, Output> {
-//     fn parse(&self, input: &'a str) -> ParseResult<'a, Output> {
-//         self.parser.parse(input)
-//     }
-// }
-
-// // parser
-// fn the_letter_a(input: &str) -> ParseResult<()> {
-//     match input.chars().next() {
-//         Some('a') => Ok((&input['a'.len_utf8()..], ())),
-//         _ => Err(input),
-//     }
-// }
-
-// // parser
-// fn any_char(input: &str) -> ParseResult<char> {
-//     match input.chars().next() {
-//         Some(next) => Ok((&input[next.len_utf8()..], next)),
-//         _ => Err(input),
-//     }
-// }
-
-// fn token<'a>() -> impl Parser<'a, char> {
-//     zero_or_more(any_char.pred(|c| c.is_alphanumeric || b"!#$%&'*+-.^_`|~".contains(c)))
-// }
-
-// fn whitespace_char<'a>() -> impl Parser<'a, char> {
-//     pred(any_char, |c| c.is_whitespace())
-// }
-
-// fn space1<'a>() -> impl Parser<'a, Vec<char>> {
-//     one_or_more(whitespace_char())
-// }
-
-// fn space0<'a>() -> impl Parser<'a, Vec<char>> {
-//     zero_or_more(whitespace_char())
-// }
-
-// fn attribute_pair<'a>() -> impl Parser<'a, (String, String)> {
-//     pair(identifier, right(match_literal("="), quoted_string()))
-// }
-
-// fn attributes<'a>() -> impl Parser<'a, Vec<(String, String)>> {
-//     zero_or_more(right(space1(), attribute_pair()))
-// }
-
-// fn quoted_string<'a>() -> impl Parser<'a, String> {
-//     right(
-//         match_literal("\""),
-//         left(
-//             zero_or_more(pred(any_char, |c| *c != '"')),
-//             match_literal("\""),
-//         ),
-//     ).map(|chars| chars.into_iter().collect())
-// }
-
-// // parser combinator
-// fn match_literal<'a>(expected: &'static str) -> impl Parser<'a, ()> {
-//     move |input: &'a str| match input.get(0..expected.len()) {
-//         Some(next) if next == expected => Ok((&input[expected.len()..], ())),
-//         _ => Err(input),
-//     }
-// }
-
-// fn identifier(input: &str) -> ParseResult<String> {
-//     let mut matched = String::new();
-//     let mut chars = input.chars();
-
-//     match chars.next() {
-//         Some(next) if next.is_alphabetic() => matched.push(next),
-//         _ => return Err(input),
-//     }
-
-//     while let Some(next) = chars.next() {
-//         if next.is_alphanumeric() || next == '-' {
-//             matched.push(next);
-//         } else {
-//             break;
-//         }
-//     }
-
-//     let next_index = matched.len();
-//     Ok((&input[next_index..], matched))
-// }
-
-// fn xx() -> impl Parser<bool> {}
-
-// fn zero_or_more<'a, P, A>(parser: P) -> impl Parser<'a, Vec<A>>
-// where
-//     P: Parser<'a, A>,
-// {
-//     move |mut input| {
-//         let mut result = Vec::new();
-
-//         while let Ok((next_input, next_item)) = parser.parse(input) {
-//             input = next_input;
-//             result.push(next_item);
-//         }
-
-//         Ok((input, result))
-//     }
-// }
-
-// fn one_or_more<'a, P, A>(parser: P) -> impl Parser<'a, Vec<A>>
-// where
-//     P: Parser<'a, A>,
-// {
-//     let x = move |mut input| {
-//         let mut result = Vec::new();
-
-//         if let Ok((next_input, first_item)) = parser.parse(input) {
-//             input = next_input;
-//             result.push(first_item);
-//         } else {
-//             return Err(input);
-//         }
-
-//         while let Ok((next_input, next_item)) = parser.parse(input) {
-//             input = next_input;
-//             result.push(next_item);
-//         }
-
-//         Ok((input, result))
-//     };
-
-//     x
-// }
-
-// // fn repeat_n<'a, P, A, R>(parser: P, range: R) -> impl Parser<'a, Vec<A>>
-// // where
-// //     P: Parser<'a, A>,
-// //     R: RangeBounds<u8>,
-// // {
-// //     move |mut input| {
-// //         let mut result = Vec::new();
-
-// //         for n in 0..range.start_bound() {
-// //             if let Ok((next_input, next_item)) = parser.parse(input) {
-// //                 input = next_input;
-// //                 result.push(next_item);
-// //             } else {
-// //                 return Err(input);
-// //             }
-// //         }
-
-// //         for n in range {
-// //             if let Ok((next_input, next_item)) = parser.parse(input) {
-// //                 input = next_input;
-// //                 result.push(next_item);
-// //             }
-// //         }
-
-// //         Ok((input, result))
-// //     }
-// // }
-
-// fn left<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R1>
-// where
-//     P1: Parser<'a, R1>,
-//     P2: Parser<'a, R2>,
-// {
-//     zip(parser1, parser2).map(|(left, _)| left)
-// }
-
-// fn right<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R2>
-// where
-//     P1: Parser<'a, R1>,
-//     P2: Parser<'a, R2>,
-// {
-//     zip(parser1, parser2).map(|(_, right)| right)
-// }
-
-// #[test]
-// fn literal_parser() {
-//     let parse_joe = match_literal("Hello Joe!");
-//     assert_eq!(Ok(("", ())), parse_joe.parse("Hello Joe!"));
-//     assert_eq!(
-//         Ok((" Hello Robert!", ())),
-//         parse_joe.parse("Hello Joe! Hello Robert!")
-//     );
-//     assert_eq!(Err("Hello Mike!"), parse_joe.parse("Hello Mike!"));
-// }
-
-// #[test]
-// fn identifier_parser() {
-//     assert_eq!(
-//         Ok(("", "i-am-an-identifier".to_string())),
-//         identifier("i-am-an-identifier")
-//     );
-//     assert_eq!(
-//         Ok((" entirely an identifier", "not".to_string())),
-//         identifier("not entirely an identifier")
-//     );
-//     assert_eq!(
-//         Err("!not at all an identifier"),
-//         identifier("!not at all an identifier")
-//     );
-// }
-
-// #[test]
-// fn right_combinator() {
-//     let tag_opener = right(match_literal("<"), identifier);
-//     assert_eq!(
-//         Ok(("/>", "my-first-element".to_string())),
-//         tag_opener.parse("<my-first-element/>")
-//     );
-//     assert_eq!(Err("oops"), tag_opener.parse("oops"));
-//     assert_eq!(Err("!oops"), tag_opener.parse("!oops"));
-// }
-
-// #[test]
-// fn one_or_more_combinator() {
-//     let parser = one_or_more(match_literal("ha"));
-//     assert_eq!(Ok(("", vec![(), (), ()])), parser.parse("hahaha"));
-//     assert_eq!(Err("ahah"), parser.parse("ahah"));
-//     assert_eq!(Err(""), parser.parse(""));
-// }
-
-// #[test]
-// fn zero_or_more_combinator() {
-//     let parser = zero_or_more(match_literal("ha"));
-//     assert_eq!(Ok(("", vec![(), (), ()])), parser.parse("hahaha"));
-//     assert_eq!(Ok(("ahah", vec![])), parser.parse("ahah"));
-//     assert_eq!(Ok(("", vec![])), parser.parse(""));
-// }
-
-// // #[test]
-// // fn repeat_n_combinator() {
-// //     let zero_or_more = repeat_n(match_literal("ha"), (0..));
-// //     assert_eq!(Ok(("", vec![(), (), ()])), zero_or_more.parse("hahaha"));
-// //     assert_eq!(Ok(("ahah", vec![])), zero_or_more.parse("ahah"));
-// //     assert_eq!(Ok(("", vec![])), zero_or_more.parse(""));
-
-// //     let one_or_more = repeat_n(match_literal("ha"), (1..));
-// //     assert_eq!(Ok(("", vec![(), (), ()])), one_or_more.parse("hahaha"));
-// //     assert_eq!(Err("ahah"), one_or_more.parse("ahah"));
-// //     assert_eq!(Err(""), one_or_more.parse(""));
-
-// //     let two_or_three = repeat_n(match_literal("ha"), (2..=3));
-// //     assert_eq!(Ok(("", vec![(), ()])), two_or_three.parse("haha"));
-// //     assert_eq!(Ok(("", vec![(), (), ()])), two_or_three.parse("hahaha"));
-// //     assert_eq!(Ok(("ha", vec![(), (), ()])), two_or_three.parse("hahahaha"));
-// //     assert_eq!(Err("ha"), two_or_three.parse("ha"));
-// // }
-
-// #[test]
-// fn predicate_combinator() {
-//     let parser = pred(any_char, |c| *c == 'o');
-//     assert_eq!(Ok(("mg", 'o')), parser.parse("omg"));
-//     assert_eq!(Err("lol"), parser.parse("lol"));
-// }
-
-// #[test]
-// fn quoted_string_parser() {
-//     assert_eq!(
-//         Ok(("", "Hello Joe!".to_string())),
-//         quoted_string().parse("\"Hello Joe!\"")
-//     );
-// }
-
-// #[test]
-// fn attribute_parser() {
-//     assert_eq!(
-//         Ok((
-//             "",
-//             vec![
-//                 ("one".to_string(), "1".to_string()),
-//                 ("two".to_string(), "2".to_string())
-//             ]
-//         )),
-//         attributes().parse(" one=\"1\" two=\"2\"")
-//     );
-// }
+// https://bodil.lol/parser-combinators/
+
+// Parsers work on bytes and hand out slices of their input instead of
+// collecting into new values, so parsing never allocates.
+
+// request-line = method SP request-target SP HTTP-version
+// field-line   = field-name ":" OWS field-value OWS
+
+/// The remaining input and the parsed value, or the input at the point
+/// where parsing failed.
+pub type ParseResult<'a, Output> = Result<(&'a [u8], Output), &'a [u8]>;
+
+pub trait Parser<'a, Output> {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<'a, Output>;
+
+    fn map<F, NewOutput>(self, function: F) -> impl Parser<'a, NewOutput>
+    where
+        Self: Sized,
+        F: Fn(Output) -> NewOutput,
+    {
+        map(self, function)
+    }
+
+    fn pred<F>(self, predicate: F) -> impl Parser<'a, Output>
+    where
+        Self: Sized,
+        F: Fn(&Output) -> bool,
+    {
+        pred(self, predicate)
+    }
+}
+
+// Any closure that takes a byte slice and returns a ParseResult is a Parser,
+// and calling some_parser.parse(input) is like calling some_parser(input)
+impl<'a, F, Output> Parser<'a, Output> for F
+where
+    F: Fn(&'a [u8]) -> ParseResult<'a, Output>,
+{
+    fn parse(&self, input: &'a [u8]) -> ParseResult<'a, Output> {
+        self(input)
+    }
+}
+
+pub fn any_byte(input: &[u8]) -> ParseResult<'_, u8> {
+    match input.split_first() {
+        Some((next, rest)) => Ok((rest, *next)),
+        None => Err(input),
+    }
+}
+
+/// Matches a single byte for which `predicate` holds.
+pub fn byte<'a, F>(predicate: F) -> impl Parser<'a, u8>
+where
+    F: Fn(u8) -> bool,
+{
+    move |input: &'a [u8]| match input.split_first() {
+        Some((next, rest)) if predicate(*next) => Ok((rest, *next)),
+        _ => Err(input),
+    }
+}
+
+pub fn match_literal<'a>(expected: &'static [u8]) -> impl Parser<'a, ()> {
+    move |input: &'a [u8]| match input.strip_prefix(expected) {
+        Some(rest) => Ok((rest, ())),
+        None => Err(input),
+    }
+}
+
+pub fn map<'a, P, F, A, B>(parser: P, function: F) -> impl Parser<'a, B>
+where
+    P: Parser<'a, A>,
+    F: Fn(A) -> B,
+{
+    move |input| {
+        parser
+            .parse(input)
+            .map(|(rest, result)| (rest, function(result)))
+    }
+}
+
+pub fn pred<'a, P, A, F>(parser: P, predicate: F) -> impl Parser<'a, A>
+where
+    P: Parser<'a, A>,
+    F: Fn(&A) -> bool,
+{
+    move |input| {
+        if let Ok((rest, value)) = parser.parse(input) {
+            if predicate(&value) {
+                return Ok((rest, value));
+            }
+        }
+
+        Err(input)
+    }
+}
+
+pub fn zip2<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, (R1, R2)>
+where
+    P1: Parser<'a, R1>,
+    P2: Parser<'a, R2>,
+{
+    move |input| {
+        parser1.parse(input).and_then(|(rest1, result1)| {
+            parser2
+                .parse(rest1)
+                .map(|(rest2, result2)| (rest2, (result1, result2)))
+        })
+    }
+}
+
+pub fn zip3<'a, P1, P2, P3, R1, R2, R3>(
+    parser1: P1,
+    parser2: P2,
+    parser3: P3,
+) -> impl Parser<'a, (R1, R2, R3)>
+where
+    P1: Parser<'a, R1>,
+    P2: Parser<'a, R2>,
+    P3: Parser<'a, R3>,
+{
+    map(
+        zip2(parser1, zip2(parser2, parser3)),
+        |(result1, (result2, result3))| (result1, result2, result3),
+    )
+}
+
+pub fn left<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R1>
+where
+    P1: Parser<'a, R1>,
+    P2: Parser<'a, R2>,
+{
+    map(zip2(parser1, parser2), |(left, _)| left)
+}
+
+pub fn right<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R2>
+where
+    P1: Parser<'a, R1>,
+    P2: Parser<'a, R2>,
+{
+    map(zip2(parser1, parser2), |(_, right)| right)
+}
+
+pub fn optional<'a, P, A>(parser: P) -> impl Parser<'a, Option<A>>
+where
+    P: Parser<'a, A>,
+{
+    move |input| match parser.parse(input) {
+        Ok((rest, result)) => Ok((rest, Some(result))),
+        Err(_) => Ok((input, None)),
+    }
+}
+
+/// Runs `parser` and returns the part of the input it consumed.
+pub fn recognize<'a, P, A>(parser: P) -> impl Parser<'a, &'a [u8]>
+where
+    P: Parser<'a, A>,
+{
+    move |input: &'a [u8]| {
+        parser
+            .parse(input)
+            .map(|(rest, _)| (rest, &input[..input.len() - rest.len()]))
+    }
+}
+
+/// Applies `parser` as often as possible and returns the part of the input
+/// it consumed.
+pub fn zero_or_more<'a, P, A>(parser: P) -> impl Parser<'a, &'a [u8]>
+where
+    P: Parser<'a, A>,
+{
+    move |input: &'a [u8]| {
+        let mut rest = input;
+
+        while let Ok((next_rest, _)) = parser.parse(rest) {
+            // A parser that doesn't consume anything would match forever
+            if next_rest.len() == rest.len() {
+                break;
+            }
+
+            rest = next_rest;
+        }
+
+        Ok((rest, &input[..input.len() - rest.len()]))
+    }
+}
+
+pub fn one_or_more<'a, P, A>(parser: P) -> impl Parser<'a, &'a [u8]>
+where
+    P: Parser<'a, A>,
+{
+    pred(zero_or_more(parser), |matched| !matched.is_empty())
+}
+
+/// Matches the input up to the next space or line break with `parser`,
+/// which has to take all of it. Bytes it can't match are reported where
+/// they are, rather than where the delimiter was expected.
+pub fn up_to_space<'a, P, A>(parser: P) -> impl Parser<'a, A>
+where
+    P: Parser<'a, A>,
+{
+    move |input: &'a [u8]| {
+        let end = input
+            .iter()
+            .position(|byte| matches!(byte, b' ' | b'\r' | b'\n'))
+            .unwrap_or(input.len());
+
+        match parser.parse(&input[..end]) {
+            Ok(([], output)) => Ok((&input[end..], output)),
+            Ok((rest, _)) | Err(rest) => Err(&input[end - rest.len()..]),
+        }
+    }
+}
+
+// tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." /
+//         "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA
+pub fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+pub fn is_vchar(byte: u8) -> bool {
+    (0x21..=0x7e).contains(&byte)
+}
+
+// field-vchar = VCHAR / obs-text
+pub fn is_field_vchar(byte: u8) -> bool {
+    is_vchar(byte) || byte >= 0x80
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t'
+}
+
+pub fn sp(input: &[u8]) -> ParseResult<'_, ()> {
+    match_literal(b" ").parse(input)
+}
+
+pub fn crlf(input: &[u8]) -> ParseResult<'_, ()> {
+    match_literal(b"\r\n").parse(input)
+}
+
+/// Optional whitespace, `OWS = *( SP / HTAB )`.
+pub fn ows(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    zero_or_more(byte(is_whitespace)).parse(input)
+}
+
+/// `token = 1*tchar`
+pub fn token(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    one_or_more(byte(is_tchar)).parse(input)
+}
+
+/// `method = token`
+pub fn method(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    up_to_space(token).parse(input)
+}
+
+/// Any run of visible characters. The forms a target can take are told
+/// apart by `RequestTarget::parse`.
+pub fn request_target(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    up_to_space(one_or_more(byte(is_vchar))).parse(input)
+}
+
+/// `HTTP-version = "HTTP/" DIGIT "." DIGIT`
+pub fn http_version(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    let digit = || byte(|byte| byte.is_ascii_digit());
+
+    recognize(zip3(
+        match_literal(b"HTTP/"),
+        digit(),
+        right(match_literal(b"."), digit()),
+    ))
+    .parse(input)
+}
+
+/// `request-line = method SP request-target SP HTTP-version`, without the
+/// line ending. Returns the method, the request-target and the version.
+pub fn request_line(input: &[u8]) -> ParseResult<'_, (&[u8], &[u8], &[u8])> {
+    zip3(left(method, sp), left(request_target, sp), http_version).parse(input)
+}
+
+/// `field-name = token`
+pub fn field_name(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    token(input)
+}
+
+/// `field-value = *field-content`, where field content starts and ends with
+/// a visible character. Whitespace around the value is not part of it.
+pub fn field_value(input: &[u8]) -> ParseResult<'_, &[u8]> {
+    // field-content = field-vchar [ 1*( SP / HTAB / field-vchar ) field-vchar ]
+    let field_content = zip2(
+        byte(is_field_vchar),
+        zero_or_more(right(ows, byte(is_field_vchar))),
+    );
+
+    recognize(optional(field_content)).parse(input)
+}
+
+/// `field-line = field-name ":" OWS field-value OWS`, without the line
+/// ending. Returns the name and the value.
+pub fn field_line(input: &[u8]) -> ParseResult<'_, (&[u8], &[u8])> {
+    zip2(
+        left(field_name, match_literal(b":")),
+        right(ows, left(field_value, ows)),
+    )
+    .parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_parser() {
+        let parse_joe = match_literal(b"Hello Joe!");
+        assert_eq!(Ok((&b""[..], ())), parse_joe.parse(b"Hello Joe!"));
+        assert_eq!(
+            Ok((&b" Hello Robert!"[..], ())),
+            parse_joe.parse(b"Hello Joe! Hello Robert!")
+        );
+        assert_eq!(Err(&b"Hello Mike!"[..]), parse_joe.parse(b"Hello Mike!"));
+    }
+
+    #[test]
+    fn right_combinator() {
+        let tag_opener = right(match_literal(b"<"), token);
+        assert_eq!(
+            Ok((&b"/>"[..], &b"my-first-element"[..])),
+            tag_opener.parse(b"<my-first-element/>")
+        );
+        assert_eq!(Err(&b"oops"[..]), tag_opener.parse(b"oops"));
+        assert_eq!(Err(&b"(oops"[..]), tag_opener.parse(b"<(oops"));
+    }
+
+    #[test]
+    fn one_or_more_combinator() {
+        let parser = one_or_more(match_literal(b"ha"));
+        assert_eq!(Ok((&b""[..], &b"hahaha"[..])), parser.parse(b"hahaha"));
+        assert_eq!(Err(&b"ahah"[..]), parser.parse(b"ahah"));
+        assert_eq!(Err(&b""[..]), parser.parse(b""));
+    }
+
+    #[test]
+    fn zero_or_more_combinator() {
+        let parser = zero_or_more(match_literal(b"ha"));
+        assert_eq!(Ok((&b""[..], &b"hahaha"[..])), parser.parse(b"hahaha"));
+        assert_eq!(Ok((&b"ahah"[..], &b""[..])), parser.parse(b"ahah"));
+        assert_eq!(Ok((&b""[..], &b""[..])), parser.parse(b""));
+
+        let never_consumes = zero_or_more(optional(match_literal(b"x")));
+        assert_eq!(Ok((&b"y"[..], &b""[..])), never_consumes.parse(b"y"));
+    }
+
+    #[test]
+    fn predicate_combinator() {
+        let parser = any_byte.pred(|byte| *byte == b'o');
+        assert_eq!(Ok((&b"mg"[..], b'o')), parser.parse(b"omg"));
+        assert_eq!(Err(&b"lol"[..]), parser.parse(b"lol"));
+    }
+
+    #[test]
+    fn map_combinator() {
+        let parser = token.map(|token| token.len());
+        assert_eq!(Ok((&b" rest"[..], 5)), parser.parse(b"hello rest"));
+    }
+
+    #[test]
+    fn token_parser() {
+        assert_eq!(Ok((&b" "[..], &b"GET"[..])), token(b"GET "));
+        assert_eq!(Ok((&b":"[..], &b"X-Custom_1"[..])), token(b"X-Custom_1:"));
+        assert_eq!(Err(&b" GET"[..]), token(b" GET"));
+        assert_eq!(Err(&b"(comment)"[..]), token(b"(comment)"));
+    }
+
+    #[test]
+    fn http_version_parser() {
+        assert_eq!(Ok((&b""[..], &b"HTTP/1.1"[..])), http_version(b"HTTP/1.1"));
+        assert!(http_version(b"HTTP/11").is_err());
+        assert!(http_version(b"http/1.1").is_err());
+    }
+
+    #[test]
+    fn request_line_parser() {
+        assert_eq!(
+            Ok((
+                &b""[..],
+                (&b"GET"[..], &b"/hello.txt?x=1"[..], &b"HTTP/1.1"[..])
+            )),
+            request_line(b"GET /hello.txt?x=1 HTTP/1.1")
+        );
+        assert!(request_line(b"GET  /hello.txt HTTP/1.1").is_err());
+        assert!(request_line(b"GET /hello.txt").is_err());
+    }
+
+    #[test]
+    fn up_to_space_combinator() {
+        let parser = up_to_space(token);
+        assert_eq!(Ok((&b" b"[..], &b"a"[..])), parser.parse(b"a b"));
+        assert_eq!(Ok((&b"\r\n"[..], &b"a"[..])), parser.parse(b"a\r\n"));
+        assert_eq!(Err(&b"{c d"[..]), parser.parse(b"ab{c d"));
+        assert_eq!(Err(&b" b"[..]), parser.parse(b" b"));
+    }
+
+    #[test]
+    fn field_value_parser() {
+        assert_eq!(Ok((&b""[..], &b"a b\tc"[..])), field_value(b"a b\tc"));
+        assert_eq!(Ok((&b" \t"[..], &b"value"[..])), field_value(b"value \t"));
+        assert_eq!(Ok((&b""[..], &b""[..])), field_value(b""));
+        assert_eq!(
+            Ok((&b""[..], &b"caf\xc3\xa9"[..])),
+            field_value(b"caf\xc3\xa9")
+        );
+    }
+
+    #[test]
+    fn field_line_parser() {
+        assert_eq!(
+            Ok((&b""[..], (&b"Host"[..], &b"example.com:8080"[..]))),
+            field_line(b"Host: example.com:8080 ")
+        );
+        assert_eq!(
+            Ok((&b""[..], (&b"Empty"[..], &b""[..]))),
+            field_line(b"Empty:")
+        );
+        assert!(field_line(b"Host : example.com").is_err());
+        assert!(field_line(b" folded").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::net::{Listener, Stream};
+
+use log::error;
+use mio::{unix::SourceFd, Events, Interest, Poll, Token, Waker};
+use std::{io, mem, net::SocketAddr, os::unix::io::AsRawFd, sync::Arc, time::Duration};
+
+const WAKER: Token = Token(0);
+const EVENTS_CAPACITY: usize = 128;
+const ACCEPT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Waits for incoming connections and for a wake-up call at the same time,
+/// so neither accepting nor stopping has to wait for a polling interval.
+pub struct Reactor {
+    poll: Poll,
+    events: Events,
+    listeners: Vec<Listener>,
+    waker: Arc<Waker>,
+}
+
+impl Reactor {
+    pub fn new(listeners: Vec<Listener>) -> io::Result<Reactor> {
+        let poll = Poll::new()?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+
+        for (index, listener) in listeners.iter().enumerate() {
+            listener.set_nonblocking(true)?;
+
+            poll.registry().register(
+                &mut SourceFd(&listener.as_raw_fd()),
+                listener_token(index),
+                Interest::READABLE,
+            )?;
+        }
+
+        Ok(Reactor {
+            poll,
+            events: Events::with_capacity(EVENTS_CAPACITY),
+            listeners,
+            waker,
+        })
+    }
+
+    /// The addresses of all TCP listeners.
+    pub fn local_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        self.listeners
+            .iter()
+            .filter_map(|listener| listener.local_addr())
+            .collect()
+    }
+
+    /// Calling `wake` on the returned waker makes `accept_until_woken` return.
+    pub fn waker(&self) -> Arc<Waker> {
+        Arc::clone(&self.waker)
+    }
+
+    /// Hands every accepted stream to `on_accept` until the waker is woken.
+    pub fn accept_until_woken<F: FnMut(Stream)>(&mut self, mut on_accept: F) -> io::Result<()> {
+        // Readiness is only reported when it changes, so listeners that
+        // stopped accepting because of an error, e.g. too many open files,
+        // are tried again until their backlog is empty
+        let mut retry = Vec::new();
+
+        loop {
+            let timeout = match retry.is_empty() {
+                true => None,
+                false => Some(ACCEPT_RETRY_INTERVAL),
+            };
+
+            if let Err(e) = self.poll.poll(&mut self.events, timeout) {
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+
+                return Err(e);
+            }
+
+            let mut ready = mem::take(&mut retry);
+
+            for event in self.events.iter() {
+                if event.token() == WAKER {
+                    return Ok(());
+                }
+
+                ready.push(event.token().0 - 1);
+            }
+
+            ready.sort_unstable();
+            ready.dedup();
+
+            for index in ready {
+                let listener = match self.listeners.get(index) {
+                    Some(listener) => listener,
+                    None => continue,
+                };
+
+                if !accept_pending(listener, &mut on_accept) {
+                    retry.push(index);
+                }
+            }
+        }
+    }
+}
+
+// Accepts until the backlog is empty. Returns `false` if accepting failed
+// before that.
+fn accept_pending<F: FnMut(Stream)>(listener: &Listener, on_accept: &mut F) -> bool {
+    loop {
+        match listener.accept() {
+            Ok(stream) => {
+                // Some platforms let accepted streams inherit the
+                // listener's non-blocking mode
+                if let Err(e) = stream.set_nonblocking(false) {
+                    error!("Could not set stream to blocking: {}", e);
+                    continue;
+                }
+
+                on_accept(stream);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                error!("Could not accept connection: {}", e);
+                return false;
+            }
+        }
+    }
+}
+
+fn listener_token(index: usize) -> Token {
+    Token(index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socket2::{Domain, Socket, Type};
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    #[test]
+    fn accept_until_woken_accepts_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut reactor = Reactor::new(vec![Listener::Tcp(listener)]).unwrap();
+        let waker = reactor.waker();
+
+        let client = thread::spawn(move || {
+            let _stream = TcpStream::connect(address).unwrap();
+            thread::sleep(Duration::from_millis(50));
+        });
+
+        let mut accepted = 0;
+        reactor
+            .accept_until_woken(|_| {
+                accepted += 1;
+                waker.wake().unwrap();
+            })
+            .unwrap();
+
+        client.join().unwrap();
+        assert_eq!(accepted, 1);
+    }
+
+    #[test]
+    fn accept_until_woken_accepts_on_all_listeners() {
+        let listeners = vec![
+            Listener::Tcp(TcpListener::bind("127.0.0.1:0").unwrap()),
+            Listener::Tcp(TcpListener::bind("[::1]:0").unwrap()),
+        ];
+        let mut reactor = Reactor::new(listeners).unwrap();
+        let addresses = reactor.local_addrs().unwrap();
+        let waker = reactor.waker();
+
+        let client = thread::spawn(move || {
+            let _streams: Vec<_> = addresses
+                .iter()
+                .map(|address| TcpStream::connect(address).unwrap())
+                .collect();
+            thread::sleep(Duration::from_millis(50));
+        });
+
+        let mut accepted = Vec::new();
+        reactor
+            .accept_until_woken(|stream| {
+                let address = match stream {
+                    Stream::Tcp(ref stream) => stream.local_addr().unwrap(),
+                    _ => unreachable!(),
+                };
+                accepted.push(address.is_ipv6());
+                if accepted.len() == 2 {
+                    waker.wake().unwrap();
+                }
+            })
+            .unwrap();
+
+        client.join().unwrap();
+        accepted.sort();
+        assert_eq!(accepted, vec![false, true]);
+    }
+
+    #[test]
+    fn accept_until_woken_returns_on_wake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut reactor = Reactor::new(vec![Listener::Tcp(listener)]).unwrap();
+        let waker = reactor.waker();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            waker.wake().unwrap();
+        });
+
+        reactor.accept_until_woken(|_| {}).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn accept_pending_reports_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let address = listener.local_addr().unwrap();
+        let listener = Listener::Tcp(listener);
+
+        let _stream = TcpStream::connect(address).unwrap();
+        let mut accepted = 0;
+        assert!(accept_pending(&listener, &mut |_| accepted += 1));
+        assert_eq!(accepted, 1);
+
+        // Accepting on a socket that isn't listening fails
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let listener = Listener::Tcp(socket.into());
+        assert!(!accept_pending(&listener, &mut |_| {}));
+    }
+}
@@ -4,30 +4,44 @@ use middleware::Middleware;
 use std::collections::HashMap;
 
 type CallbackFunction = fn(&http::Request) -> http::Response;
+type PrecheckFunction = fn(&http::Request) -> Option<http::Response>;
 
+#[derive(Default)]
 pub struct Router {
-    routes: HashMap<http::Uri, HashMap<http::Method, CallbackFunction>>,
+    // Keyed by the normalized path, so the query doesn't affect routing
+    routes: HashMap<String, HashMap<http::Method, CallbackFunction>>,
+    prechecks: HashMap<String, HashMap<http::Method, PrecheckFunction>>,
 }
 
 impl Router {
     pub fn new() -> Self {
         Self {
             routes: HashMap::new(),
+            prechecks: HashMap::new(),
         }
     }
 
+    /// Registers `f` for requests to `path`. The path is normalized the
+    /// same way request paths are.
+    ///
+    /// # Panics
+    ///
+    /// If `path` isn't a valid absolute path.
     pub fn register(&mut self, path: &str, method: http::Method, f: CallbackFunction) {
-        let route = self
-            .routes
-            .entry(http::Uri {
-                path: path.to_string(),
-            })
-            .or_insert(HashMap::new());
+        let route = self.routes.entry(normalize(path)).or_default();
+        route.insert(method, f);
+    }
+
+    /// Registers `f` to look at requests to `path` that wait for
+    /// `100 Continue`, before their body was read. A response it returns is
+    /// sent instead of reading the body.
+    pub fn register_precheck(&mut self, path: &str, method: http::Method, f: PrecheckFunction) {
+        let route = self.prechecks.entry(normalize(path)).or_default();
         route.insert(method, f);
     }
 
     pub fn dispatch(&self, req: &http::Request) -> Result<http::Response, middleware::Error> {
-        match self.routes.get(&req.uri) {
+        match self.routes.get(&req.uri.path) {
             Some(route) => match route.get(&req.method) {
                 Some(f) => Ok(f(req)),
                 None => Err(middleware::Error::MethodNotAllowed),
@@ -37,25 +51,49 @@ impl Router {
     }
 }
 
-impl<'a> Middleware for Router {
+// A path that could never match a request is a mistake in the application
+fn normalize(path: &str) -> String {
+    match http::Uri::parse(path) {
+        Ok(uri) => uri.path,
+        Err(_) => panic!("Invalid route path: {}", path),
+    }
+}
+
+impl Middleware for Router {
     fn answer(&self, request: &http::Request) -> Result<http::Response, middleware::Error> {
         self.dispatch(request)
     }
+
+    fn supports_method(&self, method: &http::Method) -> bool {
+        self.routes.values().any(|route| route.contains_key(method))
+    }
+
+    fn handles_head(&self, request: &http::Request) -> bool {
+        self.routes
+            .get(&request.uri.path)
+            .is_some_and(|route| route.contains_key(&http::Method::Head))
+    }
+
+    fn precheck(&self, request: &http::Request) -> Option<http::Response> {
+        let f = self
+            .prechecks
+            .get(&request.uri.path)?
+            .get(&request.method)?;
+        f(request)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use http::{Request, Response, Status};
-    use mime;
 
     // fn create_dummy_request() -> Request {
     //     Request::get("/test")
     // }
 
     fn create_dummy_response() -> Response {
-        let x = Response::new(Status::Ok).body("Hello, test!", mime::TEXT_PLAIN);
-        x
+        Response::new(Status::Ok).body("Hello, test!", mime::TEXT_PLAIN)
     }
 
     #[test]
@@ -64,7 +102,7 @@ mod tests {
         router.register("/test", http::Method::Get, |_| create_dummy_response());
 
         let dummy_request = Request::get("/test");
-        let route = router.routes.get(&dummy_request.uri).unwrap();
+        let route = router.routes.get(&dummy_request.uri.path).unwrap();
         let function = route.get(&dummy_request.method).unwrap();
         let response = function(&dummy_request);
 
@@ -103,4 +141,44 @@ mod tests {
 
         assert_eq!(result, Err(middleware::Error::MethodNotAllowed));
     }
+
+    #[test]
+    fn dispatch_ignores_query_and_normalizes_path() {
+        let mut router = Router::new();
+        router.register("/hello", http::Method::Get, |_| create_dummy_response());
+
+        for target in &["/hello?name=x", "//hello", "/static/../hello", "/hell%6F"] {
+            let dummy_request = Request::get(target);
+            assert_eq!(router.dispatch(&dummy_request), Ok(create_dummy_response()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid route path")]
+    fn register_rejects_invalid_path() {
+        let mut router = Router::new();
+        router.register("hello", http::Method::Get, |_| create_dummy_response());
+    }
+
+    #[test]
+    fn precheck_runs_registered_function() {
+        let mut router = Router::new();
+        router.register("/upload", http::Method::Post, |_| create_dummy_response());
+        router.register_precheck("/upload", http::Method::Post, |request| {
+            match request.header_value("Authorization") {
+                Some(_) => None,
+                None => Some(Response::new(Status::Unauthorized)),
+            }
+        });
+
+        let mut dummy_request = Request::post("/upload");
+        assert_eq!(
+            router.precheck(&dummy_request),
+            Some(Response::new(Status::Unauthorized))
+        );
+
+        dummy_request = dummy_request.header(("Authorization", "Basic YTpi"));
+        assert_eq!(router.precheck(&dummy_request), None);
+        assert_eq!(router.precheck(&Request::get("/upload")), None);
+    }
 }
@@ -0,0 +1,425 @@
+use crate::{
+    connection,
+    http::{Response, Status},
+    net::{Listener, Stream},
+    reactor::Reactor,
+    Application, Config,
+};
+
+#[cfg(feature = "tls")]
+use crate::tls;
+
+use crossbeam::{
+    channel::{self, TrySendError},
+    scope,
+};
+use log::{debug, error, info, warn};
+use mio::Waker;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    io,
+    net::{Shutdown, SocketAddr, TcpListener},
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+const LISTEN_BACKLOG: i32 = 1024;
+const RETRY_AFTER_SECONDS: &str = "1";
+const REJECT_TIMEOUT: Duration = Duration::from_millis(50);
+// Connections beyond this many waiting to be rejected are closed right away
+const REJECT_QUEUE_SIZE: usize = 32;
+
+/// A bound server that hasn't started accepting connections yet.
+pub struct Server<'a> {
+    application: &'a Application,
+    config: Config,
+    reactor: Reactor,
+    local_addrs: Vec<SocketAddr>,
+}
+
+impl<'a> Server<'a> {
+    pub(crate) fn bind(application: &'a Application, config: Config) -> io::Result<Server<'a>> {
+        info!(
+            "Starting {} {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        let mut listeners = config
+            .addresses
+            .iter()
+            .map(|address| bind_tcp(*address).map(Listener::Tcp))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        #[cfg(feature = "tls")]
+        if !config.tls_addresses.is_empty() {
+            let tls_config = tls::server_config(&config.tls_certificates)?;
+
+            for address in &config.tls_addresses {
+                let listener = bind_tcp(*address)?;
+                listeners.push(Listener::Tls(listener, Arc::clone(&tls_config)));
+            }
+        }
+
+        if let Some(socket) = &config.unix_socket {
+            listeners.push(Listener::bind_unix(&socket.path, socket.mode)?);
+        }
+
+        // A server without listeners would run without ever being reachable
+        if listeners.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No address to listen on",
+            ));
+        }
+
+        for listener in &listeners {
+            info!("Listening at: {}", listener);
+        }
+
+        let reactor = Reactor::new(listeners)?;
+        let local_addrs = reactor.local_addrs()?;
+
+        Ok(Server {
+            application,
+            config,
+            reactor,
+            local_addrs,
+        })
+    }
+
+    /// The TCP addresses the server is bound to, in the order they were
+    /// configured, plain addresses before TLS ones. Addresses configured
+    /// with port 0 carry the port that was actually assigned.
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            waker: self.reactor.waker(),
+        }
+    }
+
+    /// Serves connections until shut down through a `ShutdownHandle`.
+    pub fn serve(self) -> ShutdownReport {
+        let Server {
+            application,
+            config,
+            mut reactor,
+            ..
+        } = self;
+
+        let (sender, receiver) = channel::bounded::<Stream>(config.queue_size);
+        let (reject_sender, reject_receiver) = channel::bounded::<Stream>(REJECT_QUEUE_SIZE);
+        let connections = Connections::default();
+        let config = &config;
+        let connections = &connections;
+
+        scope(move |s| {
+            for _ in 0..config.workers {
+                let receiver = receiver.clone();
+
+                s.spawn(move |_| {
+                    for stream in receiver.iter() {
+                        let tracker = match connections.track(&stream) {
+                            Ok(tracker) => tracker,
+                            Err(e) => {
+                                error!("Could not track connection: {}", e);
+                                continue;
+                            }
+                        };
+
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            application.handle_client(stream, config, &tracker)
+                        }));
+
+                        if result.is_err() {
+                            error!("Worker panicked while handling a connection");
+                        }
+                    }
+                });
+            }
+
+            // Rejecting takes a while, which would hold up accepting under
+            // exactly the load it is meant for
+            s.spawn(move |_| {
+                for stream in reject_receiver.iter() {
+                    reject_client(stream, config);
+                }
+            });
+
+            let result = reactor.accept_until_woken(|stream| {
+                debug!("Handling new stream");
+
+                if let Err(TrySendError::Full(stream)) = sender.try_send(stream) {
+                    if let Err(TrySendError::Full(_)) = reject_sender.try_send(stream) {
+                        warn!("Too many connections to reject, closing connection");
+                    }
+                }
+            });
+
+            match result {
+                Ok(()) => info!("Shutting down"),
+                Err(e) => error!("Reactor failed: {}", e),
+            }
+
+            // Stops accepting and lets the workers finish once the queue has
+            // been drained
+            drop(reactor);
+            drop(sender);
+            drop(reject_sender);
+
+            connections.close_idle();
+            let forcibly_closed = connections.drain(config.shutdown_grace_period);
+
+            if forcibly_closed > 0 {
+                warn!("Forcibly closed {} connection(s)", forcibly_closed);
+            }
+
+            ShutdownReport { forcibly_closed }
+        })
+        .unwrap()
+    }
+}
+
+/// Stops a running server. Can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    waker: Arc<Waker>,
+}
+
+impl ShutdownHandle {
+    /// Stops accepting connections, closes idle ones and gives active ones
+    /// the configured grace period to finish.
+    pub fn shutdown(&self) {
+        if let Err(e) = self.waker.wake() {
+            error!("Could not signal shutdown: {}", e);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ShutdownReport {
+    /// Connections that were still busy when the grace period ran out.
+    pub forcibly_closed: usize,
+}
+
+#[derive(Default)]
+struct State {
+    shutting_down: bool,
+    next_id: usize,
+    open: HashMap<usize, TrackedStream>,
+}
+
+struct TrackedStream {
+    stream: Stream,
+    idle: bool,
+}
+
+/// Keeps track of open connections so they can be closed on shutdown.
+#[derive(Default)]
+struct Connections {
+    state: Mutex<State>,
+    closed: Condvar,
+}
+
+impl Connections {
+    fn track(&self, stream: &Stream) -> io::Result<Tracker<'_>> {
+        let stream = stream.try_clone_socket()?;
+        let mut state = self.state.lock().unwrap();
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.open.insert(id, TrackedStream { stream, idle: true });
+
+        Ok(Tracker {
+            connections: self,
+            id,
+        })
+    }
+
+    fn close_idle(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.shutting_down = true;
+
+        // Wakes up workers blocked on reading the next request
+        for tracked in state.open.values().filter(|tracked| tracked.idle) {
+            let _ = tracked.stream.shutdown(Shutdown::Read);
+        }
+    }
+
+    // Waits for open connections to close and returns how many had to be
+    // closed forcibly after the grace period
+    fn drain(&self, grace_period: Duration) -> usize {
+        let deadline = Instant::now() + grace_period;
+        let mut state = self.state.lock().unwrap();
+
+        while !state.open.is_empty() {
+            let now = Instant::now();
+
+            if now >= deadline {
+                break;
+            }
+
+            state = self.closed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+
+        for tracked in state.open.values() {
+            let _ = tracked.stream.shutdown(Shutdown::Both);
+        }
+
+        state.open.len()
+    }
+}
+
+/// Reports the state of a single connection to `Connections`.
+pub(crate) struct Tracker<'a> {
+    connections: &'a Connections,
+    id: usize,
+}
+
+impl<'a> Tracker<'a> {
+    /// Marks the connection as waiting for the next request. Returns `false`
+    /// if the server is shutting down and the connection should be closed.
+    pub(crate) fn wait_for_request(&self) -> bool {
+        self.set_idle(true)
+    }
+
+    /// Marks the connection as busy with a request.
+    pub(crate) fn start_request(&self) {
+        self.set_idle(false);
+    }
+
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        self.connections.state.lock().unwrap().shutting_down
+    }
+
+    fn set_idle(&self, idle: bool) -> bool {
+        let mut state = self.connections.state.lock().unwrap();
+
+        if let Some(tracked) = state.open.get_mut(&self.id) {
+            tracked.idle = idle;
+        }
+
+        !state.shutting_down
+    }
+}
+
+impl<'a> Drop for Tracker<'a> {
+    fn drop(&mut self) {
+        let mut state = self.connections.state.lock().unwrap();
+        state.open.remove(&self.id);
+        self.connections.closed.notify_all();
+    }
+}
+
+fn bind_tcp(address: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+
+    // IPv6 sockets accept IPv4 connections by default on some platforms,
+    // which would clash with a separate IPv4 listener on the same port
+    if address.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+
+    socket.bind(&address.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+
+    Ok(socket.into())
+}
+
+// Answers connections that can't be queued so that clients back off instead
+// of waiting for a worker to become available
+fn reject_client(mut stream: Stream, config: &Config) {
+    warn!("All workers busy, rejecting connection");
+
+    // Writing to a TLS stream first finishes the handshake, which waits for
+    // the client, so those connections are just closed
+    #[cfg(feature = "tls")]
+    if let Stream::Tls(_) = stream {
+        return;
+    }
+
+    let mut response = Response::new(Status::ServiceUnavailable)
+        .header(("Retry-After", RETRY_AFTER_SECONDS))
+        .header(("Connection", "close"));
+    connection::add_standard_headers(&mut response, config);
+
+    let _ = stream.set_write_timeout(Some(REJECT_TIMEOUT));
+
+    if let Err(e) = response.write_to(&mut stream) {
+        debug!("Could not reject connection: {}", e);
+    }
+
+    stream.linger(REJECT_TIMEOUT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::prelude::*, net::TcpStream, thread};
+
+    fn stream_pair() -> (TcpStream, Stream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, Stream::Tcp(server))
+    }
+
+    #[test]
+    fn close_idle_wakes_up_idle_connections() {
+        let connections = Connections::default();
+        let (_client, mut server) = stream_pair();
+        let tracker = connections.track(&server).unwrap();
+
+        assert!(tracker.wait_for_request());
+        connections.close_idle();
+
+        let mut buffer = [0; 16];
+        assert_eq!(server.read(&mut buffer).unwrap(), 0);
+        assert!(!tracker.wait_for_request());
+    }
+
+    #[test]
+    fn drain_waits_for_busy_connections() {
+        let connections = Connections::default();
+        let (_client, server) = stream_pair();
+
+        thread::scope(|s| {
+            let tracker = connections.track(&server).unwrap();
+            tracker.start_request();
+
+            s.spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                drop(tracker);
+            });
+
+            connections.close_idle();
+            assert_eq!(connections.drain(Duration::from_secs(5)), 0);
+        });
+    }
+
+    #[test]
+    fn drain_closes_connections_after_grace_period() {
+        let connections = Connections::default();
+        let (mut client, server) = stream_pair();
+        let tracker = connections.track(&server).unwrap();
+        tracker.start_request();
+
+        connections.close_idle();
+        assert_eq!(connections.drain(Duration::from_millis(20)), 1);
+
+        let mut buffer = [0; 16];
+        assert_eq!(client.read(&mut buffer).unwrap(), 0);
+    }
+}
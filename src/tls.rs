@@ -0,0 +1,105 @@
+use crate::TlsCertificate;
+
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    ServerConfig,
+};
+use std::{collections::HashMap, fmt, io, path::Path, sync::Arc};
+
+const ALPN_HTTP_1_1: &[u8] = b"http/1.1";
+
+/// Builds the rustls configuration shared by all TLS connections.
+pub fn server_config(certificates: &[TlsCertificate]) -> io::Result<Arc<ServerConfig>> {
+    let provider = Arc::new(ring::default_provider());
+
+    let mut resolver = CertificateResolver {
+        default: None,
+        by_hostname: HashMap::new(),
+    };
+
+    for certificate in certificates {
+        let key = load_certified_key(&certificate.cert_path, &certificate.key_path, &provider)?;
+
+        match &certificate.hostname {
+            Some(hostname) => {
+                resolver
+                    .by_hostname
+                    .insert(hostname.to_ascii_lowercase(), key);
+            }
+            None => resolver.default = Some(key),
+        }
+    }
+
+    if resolver.default.is_none() && resolver.by_hostname.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "TLS addresses configured without any certificate",
+        ));
+    }
+
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_data)?
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(resolver));
+
+    config.alpn_protocols = vec![ALPN_HTTP_1_1.to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn load_certified_key(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> io::Result<Arc<CertifiedKey>> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .map_err(invalid_data)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid_data)?;
+
+    if chain.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No certificates found in {}", cert_path.display()),
+        ));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(invalid_data)?;
+    let key = CertifiedKey::from_der(chain, key, provider).map_err(invalid_data)?;
+
+    Ok(Arc::new(key))
+}
+
+fn invalid_data<E: fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Picks the certificate matching the hostname a client asked for through
+/// SNI, falling back to the default certificate.
+struct CertificateResolver {
+    default: Option<Arc<CertifiedKey>>,
+    by_hostname: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        client_hello
+            .server_name()
+            .and_then(|name| self.by_hostname.get(&name.to_ascii_lowercase()))
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+impl fmt::Debug for CertificateResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateResolver")
+            .field("default", &self.default.is_some())
+            .field("hostnames", &self.by_hostname.keys())
+            .finish()
+    }
+}
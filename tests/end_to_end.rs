@@ -1,57 +1,565 @@
-#![feature(test)]
-use mime;
 use std::{
+    env, fs,
     io::{Read, Write},
-    net::TcpStream,
-    str,
+    net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    process, str,
     sync::mpsc,
     thread,
+    time::{Duration, Instant},
 };
 use turbo_bernd::{
     self, http,
     middleware::{FileMiddleware, Middleware},
     routing::Router,
+    server::{ShutdownHandle, ShutdownReport},
     Application, Config,
 };
 
 #[test]
 fn e2e() {
-    let mut router = Router::new();
-
-    router.register("/hello", http::Method::Get, |_| {
-        http::Response::new(http::Status::Ok).body("Hello, world!", mime::TEXT_PLAIN)
-    });
-
-    let config = Config::new(5000);
-
-    let (tx, rx) = mpsc::channel();
-
-    let handle = thread::spawn(move || {
-        let router = Box::new(router);
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || {
         let file_middleware = Box::new(FileMiddleware::new("tests/mock"));
-        let middleware: Vec<Box<dyn Middleware>> = vec![router, file_middleware];
-        Application::new(middleware).run_graceful(config, rx);
+        vec![Box::new(hello_router()), file_middleware]
     });
 
-    let url = "localhost:5000";
+    let url = addresses[0];
 
-    // TODO: Fix "\r\n\r\n" being necessary
-    let request_1 = "GET /hello HTTP/1.1\r\n\r\n";
+    let request_1 = "GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
     let response_1 = make_request(url, request_1);
     assert!(response_1.starts_with("HTTP/1.1 200 OK\r\n"));
     assert!(response_1.ends_with("Hello, world!"));
 
-    let request_2 = "GET /test_one/test.html HTTP/1.1\r\n\r\n";
+    let request_2 = "GET /test_one/test.html HTTP/1.1\r\n\
+        Host: example.com\r\nConnection: close\r\n\r\n";
     let response_2 = make_request(url, request_2);
     assert!(response_2.starts_with("HTTP/1.1 200 OK\r\n"));
     assert!(response_2.ends_with("</html>\n"));
 
+    let request_query = "GET /hello?name=x HTTP/1.1\r\n\
+        Host: example.com\r\nConnection: close\r\n\r\n";
+    let response_query = make_request(url, request_query);
+    assert!(response_query.ends_with("Hello, world!"));
+
+    let request_encoded = "GET /test_one/../test_one/test%2Ehtml HTTP/1.1\r\n\
+            Host: example.com\r\nConnection: close\r\n\r\n";
+    let response_encoded = make_request(url, request_encoded);
+    assert!(response_encoded.ends_with("</html>\n"));
+
+    let request_3 = format!(
+        "GET /hello HTTP/1.1\r\nHost: example.com\r\nX-Padding: {}\r\nConnection: close\r\n\r\n",
+        "a".repeat(2048)
+    );
+    let response_3 = make_request(url, &request_3);
+    assert!(response_3.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // Pipelined requests are answered in order on the same connection
+    let request_4 = "GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n\
+        GET /test_one/test.html HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
+    let response_4 = make_request(url, request_4);
+    assert_eq!(response_4.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+    assert!(response_4.find("Hello, world!") < response_4.find("</html>"));
+    assert!(response_4.ends_with("</html>\n"));
+
     // Send terminate signal to application thread
-    let _ = tx.send(()).unwrap();
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn serves_binary_bodies() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || vec![Box::new(echo_router())]);
+
+    let body = [0x00, 0xff, 0xfe, 0x0d, 0x0a];
+    let mut request = format!(
+        "POST /echo HTTP/1.1\r\n\
+            Host: example.com\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(&body);
+
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    stream.write_all(&request).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(&body));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn decodes_chunked_uploads() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || vec![Box::new(echo_router())]);
+
+    let request = "POST /echo HTTP/1.1\r\n\
+        Host: example.com\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+        7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n";
+    let response = make_request(addresses[0], request);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Length: 13\r\n"));
+    assert!(response.ends_with("\r\n\r\nHello, world!"));
+
+    let request = "POST /echo HTTP/1.1\r\n\
+        Host: example.com\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n\
+        0\r\n\r\n";
+    let response = make_request(addresses[0], request);
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn serves_http_1_0_clients() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+
+    // Closed after the response unless the client asks otherwise
+    let response = make_request(addresses[0], "GET /hello HTTP/1.0\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(response.contains("Connection: close\r\n"));
+    assert!(response.ends_with("Hello, world!"));
+
+    let request = "GET /hello HTTP/1.0\r\nConnection: keep-alive\r\n\r\n\
+        GET /hello HTTP/1.0\r\n\r\n";
+    let response = make_request(addresses[0], request);
+    assert_eq!(response.matches("HTTP/1.0 200 OK\r\n").count(), 2);
+    assert!(response.contains("Connection: keep-alive\r\n"));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn answers_head_without_body() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+
+    // The next response on the connection has to follow right after the
+    // headers
+    let request = "HEAD /hello HTTP/1.1\r\nHost: example.com\r\n\r\n\
+        GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
+    let response = make_request(addresses[0], request);
+    assert_eq!(response.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+    assert_eq!(response.matches("Content-Length: 13\r\n").count(), 2);
+    assert_eq!(response.matches("Hello, world!").count(), 1);
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn continues_or_rejects_expecting_uploads() {
+    let config = Config::new(0).max_body_size(16);
+    let (shutdown, addresses, handle) = start_application(config, || {
+        let mut router = echo_router();
+        router.register_precheck("/echo", http::Method::Post, |request| {
+            match request.header_value("Authorization") {
+                Some(_) => None,
+                None => Some(http::Response::new(http::Status::Unauthorized)),
+            }
+        });
+        vec![Box::new(router)]
+    });
+
+    // The body is only sent once the server asked for it
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    stream
+        .write_all(
+            b"POST /echo HTTP/1.1\r\nHost: example.com\r\nAuthorization: Basic YTpi\r\n\
+            Expect: 100-continue\r\nContent-Length: 5\r\nConnection: close\r\n\r\n",
+        )
+        .unwrap();
+    let mut interim = [0; 25];
+    stream.read_exact(&mut interim).unwrap();
+    assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+    stream.write_all(b"Hello").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("Hello"));
+
+    let response = make_request(
+        addresses[0],
+        "POST /echo HTTP/1.1\r\n\
+            Host: example.com\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    assert!(response.contains("Connection: close\r\n"));
+
+    // Bodies that are too large are turned down from the header as well
+    let response = make_request(
+        addresses[0],
+        "POST /echo HTTP/1.1\r\nHost: example.com\r\nAuthorization: Basic YTpi\r\n\
+        Expect: 100-continue\r\nContent-Length: 1000\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+    assert!(!response.contains("100 Continue"));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn rejects_uploads_sent_without_waiting() {
+    let (shutdown, addresses, handle) = start_application(Config::new(0), || {
+        let mut router = echo_router();
+        router.register_precheck("/echo", http::Method::Post, |_| {
+            Some(http::Response::new(http::Status::Unauthorized))
+        });
+        vec![Box::new(router)]
+    });
+
+    // Clients stop waiting for 100 Continue after a while, so the body may
+    // already be on its way when the request is turned down
+    let body = vec![b'a'; 512 * 1024];
+    let mut request = format!(
+        "POST /echo HTTP/1.1\r\nHost: example.com\r\n\
+        Expect: 100-continue\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(&body);
+
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    let writer = {
+        let mut stream = stream.try_clone().unwrap();
+        thread::spawn(move || {
+            let _ = stream.write_all(&request);
+            let _ = stream.shutdown(Shutdown::Write);
+        })
+    };
+    thread::sleep(Duration::from_millis(100));
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+    writer.join().unwrap();
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn sends_date_and_server_headers() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || {
+        let mut router = hello_router();
+        router.register("/custom", http::Method::Get, |_| {
+            http::Response::new(http::Status::Ok)
+                .header(("Date", "Sun, 06 Nov 1994 08:49:37 GMT"))
+                .header(("Server", "custom"))
+        });
+        vec![Box::new(router)]
+    });
+
+    let response = make_request(addresses[0], "GET /hello HTTP/1.0\r\n\r\n");
+    assert!(response.contains("\r\nDate: "));
+    assert!(response.contains(&format!(
+        "\r\nServer: turbo_bernd/{}\r\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+
+    // Errors get them as well
+    let response = make_request(addresses[0], "GET /missing HTTP/1.0\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.0 404 Not Found\r\n"));
+    assert!(response.contains("\r\nDate: "));
+
+    let response = make_request(addresses[0], "GET /custom HTTP/1.0\r\n\r\n");
+    assert!(response.contains("\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n"));
+    assert_eq!(response.matches("Date: ").count(), 1);
+    assert!(response.contains("\r\nServer: custom\r\n"));
+    assert_eq!(response.matches("Server: ").count(), 1);
+
+    shutdown.shutdown();
+    let _ = handle.join();
+
+    let config = Config::new(0).server_header(None);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+    let response = make_request(addresses[0], "GET /hello HTTP/1.0\r\n\r\n");
+    assert!(response.contains("\r\nDate: "));
+    assert!(!response.contains("Server: "));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn explains_parse_errors_when_enabled() {
+    let request = "GET / HTTP/1.1\r\nHost : example.com\r\n\r\n";
+
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+    let response = make_request(addresses[0], request);
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(!response.contains("Missing colon"));
+    shutdown.shutdown();
+    let _ = handle.join();
+
+    let config = Config::new(0).detailed_errors(true);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+    let response = make_request(addresses[0], request);
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(response.ends_with("\r\n\r\nMissing colon after header name at byte 20\n"));
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn idle_client_does_not_block_others() {
+    let config = Config::new(0).workers(2);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+    let url = addresses[0];
+
+    // Holds on to a worker by never sending a request
+    let idle = TcpStream::connect(url).unwrap();
+
+    let request = "GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
+    let response = make_request(url, request);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    shutdown.shutdown();
+    drop(idle);
+    let _ = handle.join();
+}
+
+#[test]
+fn full_queue_is_rejected() {
+    let config = Config::new(0).workers(1).queue_size(1);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+    let url = addresses[0];
+
+    // One connection occupies the worker, the next one waits in the queue
+    let busy = TcpStream::connect(url).unwrap();
+    thread::sleep(Duration::from_millis(300));
+    let queued = TcpStream::connect(url).unwrap();
+    thread::sleep(Duration::from_millis(300));
+
+    let response = make_request(url, "GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(response.contains("Retry-After: 1\r\n"));
+
+    shutdown.shutdown();
+    drop(busy);
+    drop(queued);
+    let _ = handle.join();
+}
+
+#[test]
+fn serves_on_all_addresses() {
+    // IPv4 and IPv6 listeners can share a port
+    let port = TcpListener::bind("[::1]:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addresses = vec![
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        SocketAddr::from((Ipv6Addr::LOCALHOST, port)),
+    ];
+    let config = Config::with_addresses(addresses.clone());
+    let (shutdown, local_addrs, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+
+    assert_eq!(local_addrs, addresses);
+
+    for url in addresses {
+        let request = "GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
+        let response = make_request(url, request);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn serves_on_unix_socket() {
+    let path = env::temp_dir().join(format!("turbo_bernd-e2e-{}.sock", process::id()));
+    let config = Config::with_addresses(vec![]).unix_socket(&path, 0o660);
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+
+    assert!(addresses.is_empty());
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream
+        .write_all(b"GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("Hello, world!"));
+
+    shutdown.shutdown();
     let _ = handle.join();
+    assert!(!path.exists());
+}
+
+#[test]
+fn shutdown_lets_active_requests_finish() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || vec![Box::new(slow_router())]);
+
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: example.com\r\n\r\n")
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    shutdown.shutdown();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Connection: close\r\n"));
+
+    assert_eq!(
+        handle.join().unwrap(),
+        ShutdownReport { forcibly_closed: 0 }
+    );
+}
+
+#[test]
+fn shutdown_lets_slow_uploads_finish() {
+    let config = Config::new(0);
+    let (shutdown, addresses, handle) = start_application(config, || vec![Box::new(echo_router())]);
+
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    stream
+        .write_all(b"POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 10\r\n\r\nHello")
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    shutdown.shutdown();
+    thread::sleep(Duration::from_millis(100));
+    stream.write_all(b"World").unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("HelloWorld"));
+
+    assert_eq!(
+        handle.join().unwrap(),
+        ShutdownReport { forcibly_closed: 0 }
+    );
+}
+
+#[test]
+fn shutdown_closes_idle_connections() {
+    let config = Config::new(0).keep_alive_timeout(Duration::from_secs(60));
+    let (shutdown, addresses, handle) =
+        start_application(config, || vec![Box::new(hello_router())]);
+
+    let mut idle = TcpStream::connect(addresses[0]).unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    let start = Instant::now();
+    shutdown.shutdown();
+
+    assert_eq!(
+        handle.join().unwrap(),
+        ShutdownReport { forcibly_closed: 0 }
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let mut buffer = [0; 16];
+    assert_eq!(idle.read(&mut buffer).unwrap(), 0);
+}
+
+#[test]
+fn shutdown_forcibly_closes_connections_after_grace_period() {
+    let config = Config::new(0).shutdown_grace_period(Duration::from_millis(100));
+    let (shutdown, addresses, handle) = start_application(config, || vec![Box::new(slow_router())]);
+
+    let mut stream = TcpStream::connect(addresses[0]).unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: example.com\r\n\r\n")
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    shutdown.shutdown();
+
+    assert_eq!(
+        handle.join().unwrap(),
+        ShutdownReport { forcibly_closed: 1 }
+    );
+}
+
+fn start_application(
+    config: Config,
+    middleware: fn() -> Vec<Box<dyn Middleware>>,
+) -> (
+    ShutdownHandle,
+    Vec<SocketAddr>,
+    thread::JoinHandle<ShutdownReport>,
+) {
+    let (tx, rx) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        let application = Application::new(middleware());
+        let server = application.bind(config).unwrap();
+        tx.send((server.shutdown_handle(), server.local_addrs().to_vec()))
+            .unwrap();
+        server.serve()
+    });
+
+    let (shutdown, addresses) = rx.recv().unwrap();
+
+    (shutdown, addresses, handle)
+}
+
+fn hello_router() -> Router {
+    let mut router = Router::new();
+
+    router.register("/hello", http::Method::Get, |_| {
+        http::Response::new(http::Status::Ok).body("Hello, world!", mime::TEXT_PLAIN)
+    });
+
+    router
+}
+
+fn echo_router() -> Router {
+    let mut router = Router::new();
+
+    router.register("/echo", http::Method::Post, |request| {
+        http::Response::new(http::Status::Ok)
+            .body(request.body.clone(), mime::APPLICATION_OCTET_STREAM)
+    });
+
+    router
+}
+
+fn slow_router() -> Router {
+    let mut router = Router::new();
+
+    router.register("/slow", http::Method::Get, |_| {
+        thread::sleep(Duration::from_millis(300));
+        http::Response::new(http::Status::Ok)
+    });
+
+    router
 }
 
-fn make_request(url: &str, req: &str) -> String {
+fn make_request(url: SocketAddr, req: &str) -> String {
     let mut stream = TcpStream::connect(url).unwrap();
     stream.write_all(req.as_bytes()).unwrap();
 
@@ -1,9 +1,7 @@
-use turbo_bernd::http::{self, Request, Response};
+use turbo_bernd::http::Request;
 use turbo_bernd::middleware::{self, FileMiddleware, Middleware};
 
-use mime;
-use std::fs::File;
-use std::io::prelude::*;
+use std::fs;
 
 #[test]
 fn answer_returns_response_with_directory_listing() {
@@ -16,7 +14,7 @@ fn answer_returns_response_with_directory_listing() {
 
     let directories = ["test_one", "test_two"];
     for entry in &directories {
-        assert!(response.body.contains(entry));
+        assert!(response.text().unwrap().contains(entry));
     }
 }
 
@@ -27,16 +25,74 @@ fn answer_returns_response_with_file() {
     };
     let dummy_request = Request::get("/test_one/test.html");
 
-    let response = file_middleware.answer(&dummy_request);
+    let response = file_middleware.answer(&dummy_request).unwrap();
+
+    let buffer = fs::read("tests/mock/test_one/test.html").unwrap();
+    let content_length = buffer.len().to_string();
+    assert_eq!(
+        response.header_value("Content-Length"),
+        Some(content_length.as_str())
+    );
+    assert_eq!(response.header_value("Content-Type"), Some("text/html"));
+    assert!(response.to_bytes().unwrap().ends_with(&buffer));
+}
 
-    let mut buffer = String::new();
-    let _ = File::open("tests/mock/test_one/test.html")
+#[test]
+fn answer_returns_response_with_binary_file() {
+    let file_middleware = FileMiddleware {
+        file_directory: "tests/mock",
+    };
+    let dummy_request = Request::get("/test_one/pixel.png");
+
+    let response = file_middleware.answer(&dummy_request).unwrap();
+
+    let buffer = fs::read("tests/mock/test_one/pixel.png").unwrap();
+    assert_eq!(response.header_value("Content-Type"), Some("image/png"));
+    assert!(response.to_bytes().unwrap().ends_with(&buffer));
+}
+
+#[test]
+fn answer_returns_headers_for_head() {
+    let file_middleware = FileMiddleware {
+        file_directory: "tests/mock",
+    };
+    let dummy_request = Request::parse(
+        b"HEAD /test_one/pixel.png HTTP/1.1\r\n\
+        Host: example.com\r\n\r\n",
+    )
+    .unwrap();
+
+    let response = file_middleware.answer(&dummy_request).unwrap();
+
+    let content_length = fs::metadata("tests/mock/test_one/pixel.png")
         .unwrap()
-        .read_to_string(&mut buffer);
+        .len()
+        .to_string();
+    assert_eq!(
+        response.header_value("Content-Length"),
+        Some(content_length.as_str())
+    );
+    assert_eq!(response.header_value("Content-Type"), Some("image/png"));
+    assert!(response.to_bytes().unwrap().ends_with(b"\r\n\r\n"));
+}
+
+#[test]
+fn answer_only_reads_files() {
+    let file_middleware = FileMiddleware {
+        file_directory: "tests/mock",
+    };
 
-    let dummy_response = Response::new(http::Status::Ok).body(&buffer, mime::TEXT_HTML);
+    let dummy_request = Request::post("/test_one/test.html");
+    assert_eq!(
+        file_middleware.answer(&dummy_request),
+        Err(middleware::Error::MethodNotAllowed)
+    );
 
-    assert_eq!(response.unwrap().body, dummy_response.body);
+    let dummy_request = Request::post("/missing.html");
+    assert_eq!(
+        file_middleware.answer(&dummy_request),
+        Err(middleware::Error::NotFound)
+    );
 }
 
 #[test]
@@ -0,0 +1,211 @@
+#![cfg(feature = "tls")]
+
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+use std::{
+    convert::TryFrom,
+    env, fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    process,
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+use turbo_bernd::{
+    http,
+    routing::Router,
+    server::{ShutdownHandle, ShutdownReport},
+    Application, Config,
+};
+
+struct TestCertificate {
+    der: CertificateDer<'static>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+// Generates a self-signed certificate for `hostname` and stores it as PEM
+fn generate_certificate(hostname: &str) -> TestCertificate {
+    let certified = rcgen::generate_simple_self_signed(vec![hostname.to_string()]).unwrap();
+
+    let directory = env::temp_dir();
+    let prefix = format!("turbo_bernd-{}-{}", process::id(), hostname);
+    let cert_path = directory.join(format!("{}.crt", prefix));
+    let key_path = directory.join(format!("{}.key", prefix));
+
+    fs::write(&cert_path, certified.cert.pem()).unwrap();
+    fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+    TestCertificate {
+        der: certified.cert.der().clone(),
+        cert_path,
+        key_path,
+    }
+}
+
+fn start_application(
+    config: Config,
+) -> (
+    ShutdownHandle,
+    SocketAddr,
+    thread::JoinHandle<ShutdownReport>,
+) {
+    let (tx, rx) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        let mut router = Router::new();
+        router.register("/hello", http::Method::Get, |_| {
+            http::Response::new(http::Status::Ok).body("Hello, world!", mime::TEXT_PLAIN)
+        });
+
+        let application = Application::new(vec![Box::new(router)]);
+        let server = application.bind(config).unwrap();
+        tx.send((server.shutdown_handle(), server.local_addrs()[0]))
+            .unwrap();
+        server.serve()
+    });
+
+    let (shutdown, address) = rx.recv().unwrap();
+
+    (shutdown, address, handle)
+}
+
+fn connect(
+    address: SocketAddr,
+    hostname: &str,
+    roots: &[&TestCertificate],
+) -> StreamOwned<ClientConnection, TcpStream> {
+    let mut root_store = RootCertStore::empty();
+    for root in roots {
+        root_store.add(root.der.clone()).unwrap();
+    }
+
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(hostname.to_string()).unwrap();
+    let connection = ClientConnection::new(Arc::new(config), server_name).unwrap();
+    StreamOwned::new(connection, TcpStream::connect(address).unwrap())
+}
+
+// Requests /hello and returns the response with the certificate the server
+// presented
+fn make_request(
+    address: SocketAddr,
+    hostname: &str,
+    roots: &[&TestCertificate],
+) -> (String, CertificateDer<'static>) {
+    let mut stream = connect(address, hostname, roots);
+
+    stream
+        .write_all(b"GET /hello HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let certificate = stream.conn.peer_certificates().unwrap()[0].clone();
+
+    (response, certificate)
+}
+
+#[test]
+fn serves_https() {
+    let certificate = generate_certificate("localhost");
+    let config = Config::with_addresses(vec![])
+        .tls_addresses(vec!["127.0.0.1:0".parse().unwrap()])
+        .tls_certificate(&certificate.cert_path, &certificate.key_path);
+    let (shutdown, address, handle) = start_application(config);
+
+    let (response, _) = make_request(address, "localhost", &[&certificate]);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("Hello, world!"));
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn selects_certificate_by_sni() {
+    let alpha = generate_certificate("alpha.test");
+    let beta = generate_certificate("beta.test");
+    let fallback = generate_certificate("fallback.test");
+    let config = Config::with_addresses(vec![])
+        .tls_addresses(vec!["127.0.0.1:0".parse().unwrap()])
+        .tls_sni_certificate("alpha.test", &alpha.cert_path, &alpha.key_path)
+        .tls_sni_certificate("beta.test", &beta.cert_path, &beta.key_path)
+        .tls_certificate(&fallback.cert_path, &fallback.key_path);
+    let (shutdown, address, handle) = start_application(config);
+    let roots = [&alpha, &beta, &fallback];
+
+    let (response, presented) = make_request(address, "alpha.test", &roots);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(presented, alpha.der);
+
+    let (response, presented) = make_request(address, "beta.test", &roots);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(presented, beta.der);
+
+    let (response, presented) = make_request(address, "fallback.test", &roots);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert_eq!(presented, fallback.der);
+
+    shutdown.shutdown();
+    let _ = handle.join();
+}
+
+#[test]
+fn bind_fails_without_certificate() {
+    let config = Config::with_addresses(vec![]).tls_addresses(vec!["127.0.0.1:0".parse().unwrap()]);
+    let application = Application::new(vec![]);
+
+    assert!(application.bind(config).is_err());
+}
+
+#[test]
+fn silent_client_does_not_block_rejections() {
+    let certificate = generate_certificate("localhost");
+    let config = Config::with_addresses(vec![])
+        .tls_addresses(vec!["127.0.0.1:0".parse().unwrap()])
+        .tls_certificate(&certificate.cert_path, &certificate.key_path)
+        .workers(1)
+        .queue_size(0);
+    let (shutdown, address, handle) = start_application(config);
+
+    // Occupies the worker with a connection that is kept open. Connections
+    // are turned away until the worker waits for them.
+    let mut buffer = [0; 1024];
+    let busy = loop {
+        let mut busy = connect(address, "localhost", &[&certificate]);
+        let answered = busy
+            .write_all(b"GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n")
+            .and_then(|_| busy.read(&mut buffer));
+
+        if let Ok(1..) = answered {
+            break busy;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    // A client that never starts the handshake is closed without an answer
+    let mut rejected = TcpStream::connect(address).unwrap();
+
+    rejected
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let result = rejected.read(&mut buffer);
+    assert!(matches!(result, Ok(0)), "{:?}", result);
+
+    shutdown.shutdown();
+    drop(busy);
+    let report = handle.join().unwrap();
+    assert_eq!(report.forcibly_closed, 0);
+}